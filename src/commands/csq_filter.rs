@@ -0,0 +1,324 @@
+use super::mcsq::{
+    get_consequence_severity, get_csq_format, get_csq_hdr_map, get_csq_layout, get_term_severity,
+    normalize_csq, schema,
+};
+use bcfutils::genes::unversioned;
+use bcfutils::read_list;
+use rust_htslib::bcf::header::HeaderRecord;
+use rust_htslib::bcf::record::Buffer;
+use rust_htslib::bcf::{Header, Read};
+use std::collections::HashSet;
+use std::process;
+use std::str;
+
+struct EntryFilter {
+    max_severity: u8,
+    biotypes: Option<HashSet<String>>,
+    canonical: bool,
+    genes: Option<HashSet<String>>,
+    transcripts: Option<HashSet<String>>,
+}
+
+impl EntryFilter {
+    /// gene matches on the symbol or, when there is one, the unversioned gene ID
+    fn matches(
+        &self,
+        consequence: &str,
+        gene: &str,
+        gene_id: &str,
+        transcript: &str,
+        biotype: &str,
+        canonical: bool,
+    ) -> bool {
+        return get_consequence_severity(consequence) <= self.max_severity
+            && self.biotypes.as_ref().is_none_or(|b| b.contains(biotype))
+            && (!self.canonical || canonical)
+            && self.genes.as_ref().is_none_or(|g| {
+                g.contains(gene) || (!gene_id.is_empty() && g.contains(unversioned(gene_id)))
+            })
+            && self
+                .transcripts
+                .as_ref()
+                .is_none_or(|t| t.contains(unversioned(transcript)));
+    }
+}
+
+/// the string values of an INFO field, missing ones are empty
+fn get_info_strings(record: &rust_htslib::bcf::Record, tag: &[u8], b: &mut Buffer) -> Vec<String> {
+    return match record.info_shared_buffer(tag, b).string() {
+        Ok(Some(vals)) => vals
+            .iter()
+            .map(|v| match *v {
+                b"." => String::new(),
+                v => str::from_utf8(v).unwrap().to_string(),
+            })
+            .collect(),
+        _ => vec![],
+    };
+}
+
+/// pick_CANONICAL per picked value, a Flag when mcsq picked per record and 0/1 otherwise
+fn get_picked_canonical(
+    record: &rust_htslib::bcf::Record,
+    n: usize,
+    is_flag: bool,
+    b: &mut Buffer,
+) -> Vec<bool> {
+    if is_flag {
+        let set = record.info_shared_buffer(b"pick_CANONICAL", b).flag().unwrap_or(false);
+        return vec![set; n];
+    }
+    return match record.info_shared_buffer(b"pick_CANONICAL", b).integer() {
+        Ok(Some(vals)) => (0..n).map(|i| vals.get(i) == Some(&1)).collect(),
+        _ => vec![false; n],
+    };
+}
+
+/// the entries to keep with --keep-bcsq-subset
+fn bcsq_subset(entries: &Vec<String>, matches: impl Fn(&str) -> bool) -> Vec<&str> {
+    // back-references can't be judged on their own, they stay with the record
+    return entries
+        .iter()
+        .filter(|e| e.starts_with('@') || matches(e))
+        .map(|e| e.as_str())
+        .collect();
+}
+
+pub fn csq_filter(
+    input: Option<&str>,
+    output: Option<&str>,
+    min_severity: Option<&str>,
+    picked: &bool,
+    biotypes: Option<&str>,
+    canonical: &bool,
+    genes: Option<&str>,
+    transcripts: Option<&str>,
+    keep_bcsq_subset: &bool,
+    threads: &usize,
+) {
+    let max_severity = match min_severity {
+        None => 99,
+        Some(term) => match get_term_severity(term) {
+            99 => {
+                eprintln!("Error: {} is not a consequence on the mcsq severity scale", term);
+                process::exit(1);
+            }
+            s => s,
+        },
+    };
+    // entries are matched unversioned, symbols like AC000061.1 keep their dot
+    let genes = read_list(genes).map(|g| {
+        g.into_iter()
+            .map(|g| match g.starts_with("ENS") {
+                true => unversioned(&g).to_string(),
+                false => g,
+            })
+            .collect()
+    });
+    let transcripts = read_list(transcripts)
+        .map(|t| t.iter().map(|t| unversioned(t).to_string()).collect());
+    let filter = EntryFilter {
+        max_severity,
+        biotypes: read_list(biotypes),
+        canonical: *canonical,
+        genes,
+        transcripts,
+    };
+
+    let mut bcf = bcfutils::get_rdr(input);
+    bcf.set_threads(threads.clone())
+        .expect("unable to set reader threads");
+    let hdrv = bcf.header();
+
+    let (csq_tag, csq_map) = get_csq_hdr_map(hdrv.header_records())
+        .expect("was not able to get BCSQ, CSQ or ANN header info line");
+    let format = get_csq_format(csq_map.get("Description").expect("csq map doesnt have \"Description\", it really should though, something funky is happening"));
+    let layout = get_csq_layout(&format);
+    let find = |names: &[&str]| format.iter().position(|f| names.contains(&f.as_str()));
+    let canonical_idx = find(&["CANONICAL"]);
+    let gene_id_idx = find(&["gene_id", "Gene", "Gene_ID"]);
+    if *canonical && !*picked && canonical_idx.is_none() {
+        eprintln!("Error: {} has no CANONICAL subfield, run it through mcsq first", csq_tag);
+        process::exit(1);
+    }
+
+    // pick_CANONICAL is a Flag when mcsq picked per record
+    let mut picked_canonical_is_flag = true;
+    if *picked {
+        let mut has_pick = false;
+        for hrec in hdrv.header_records() {
+            if let HeaderRecord::Info { values, .. } = hrec {
+                match values.get("ID").map(|id| id.as_str()) {
+                    Some("pick_Consequence") => has_pick = true,
+                    Some("pick_CANONICAL") => {
+                        picked_canonical_is_flag = values.get("Type").map(|t| t.as_str()) == Some("Flag")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !has_pick {
+            eprintln!("Error: --picked needs the pick_ fields, run it through mcsq first");
+            process::exit(1);
+        }
+    }
+
+    let hdr = Header::from_template(&hdrv);
+    let mut obcf = bcfutils::get_wrtr(output, &hdr);
+    obcf.set_threads(threads.clone())
+        .expect("unable to set writer threads");
+
+    let mut b = Buffer::new();
+    for record_result in bcf.records() {
+        let mut record = record_result.expect("fail to read record");
+        obcf.translate(&mut record);
+        let entries = get_info_strings(&record, csq_tag.as_bytes(), &mut b);
+
+        let entry_matches = |e: &str| -> bool {
+            let norm = normalize_csq(e, &layout);
+            let f = norm.split('|').collect::<Vec<&str>>();
+            let raw = e.split('|').collect::<Vec<&str>>();
+            let get = |idx: Option<usize>| idx.and_then(|i| raw.get(i).copied()).unwrap_or("");
+            return filter.matches(
+                f[schema::CONSEQUENCE],
+                f[schema::GENE],
+                get(gene_id_idx),
+                f[schema::TRANSCRIPT],
+                f[schema::BIOTYPE],
+                get(canonical_idx) == "YES",
+            );
+        };
+
+        let keep = match picked {
+            false => entries.iter().any(|e| entry_matches(e)),
+            true => {
+                let csqs = get_info_strings(&record, b"pick_Consequence", &mut b);
+                let genes = get_info_strings(&record, b"pick_gene", &mut b);
+                let gene_ids = get_info_strings(&record, b"pick_gene_id", &mut b);
+                let trns = get_info_strings(&record, b"pick_transcript", &mut b);
+                let bts = get_info_strings(&record, b"pick_biotype", &mut b);
+                let canons =
+                    get_picked_canonical(&record, csqs.len(), picked_canonical_is_flag, &mut b);
+                let at = |v: &Vec<String>, i: usize| v.get(i).cloned().unwrap_or_default();
+                (0..csqs.len()).any(|i| {
+                    !csqs[i].is_empty()
+                        && filter.matches(
+                            &csqs[i],
+                            &at(&genes, i),
+                            &at(&gene_ids, i),
+                            &at(&trns, i),
+                            &at(&bts, i),
+                            canons[i],
+                        )
+                })
+            }
+        };
+        if !keep {
+            continue;
+        }
+
+        if *keep_bcsq_subset {
+            let subset = bcsq_subset(&entries, entry_matches);
+            match subset.is_empty() {
+                true => record
+                    .clear_info_string(csq_tag.as_bytes())
+                    .expect("failed to clear csq info field"),
+                false => record
+                    .push_info_string(csq_tag.as_bytes(), &[subset.join(",").as_bytes()])
+                    .expect("failed to set csq info field"),
+            }
+        }
+        obcf.write(&record).expect("failed to write record");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_ignores_star_prefix_and_variant_suffix() {
+        let missense = get_term_severity("missense_variant");
+        assert!(missense < 99);
+        assert_eq!(get_term_severity("missense"), missense);
+        assert_eq!(get_term_severity("*missense"), missense);
+        assert_eq!(get_consequence_severity("*missense&intron"), missense);
+        assert_eq!(get_term_severity("not_a_term"), 99);
+    }
+
+    fn set(items: &[&str]) -> Option<HashSet<String>> {
+        return Some(items.iter().map(|i| i.to_string()).collect());
+    }
+
+    fn filter() -> EntryFilter {
+        return EntryFilter {
+            max_severity: 99,
+            biotypes: None,
+            canonical: false,
+            genes: None,
+            transcripts: None,
+        };
+    }
+
+    #[test]
+    fn severity_cutoff() {
+        let f = EntryFilter {
+            max_severity: get_term_severity("missense"),
+            ..filter()
+        };
+        let ok = |c: &str| f.matches(c, "BRCA2", "", "ENST00000380152", "protein_coding", false);
+        assert!(ok("missense"));
+        assert!(ok("stop_gained"));
+        assert!(ok("synonymous&missense_variant"));
+        assert!(!ok("synonymous"));
+        assert!(!ok("intron_variant"));
+    }
+
+    #[test]
+    fn biotype_and_canonical() {
+        let f = EntryFilter {
+            biotypes: set(&["protein_coding"]),
+            canonical: true,
+            ..filter()
+        };
+        assert!(f.matches("missense", "BRCA2", "", "ENST00000380152", "protein_coding", true));
+        assert!(!f.matches("missense", "BRCA2", "", "ENST00000380152", "protein_coding", false));
+        assert!(!f.matches("missense", "BRCA2", "", "ENST00000380152", "nonsense_mediated_decay", true));
+    }
+
+    #[test]
+    fn gene_by_symbol_or_unversioned_id() {
+        let f = EntryFilter {
+            genes: set(&["BRCA2", "ENSG00000012048"]),
+            ..filter()
+        };
+        assert!(f.matches("missense", "BRCA2", "", "ENST00000380152", "protein_coding", false));
+        assert!(f.matches("missense", "BRCA1", "ENSG00000012048.23", "ENST00000357654", "protein_coding", false));
+        assert!(!f.matches("missense", "TP53", "ENSG00000141510.18", "ENST00000269305", "protein_coding", false));
+        // no gene ID subfield doesn't match an empty entry
+        assert!(!f.matches("missense", "TP53", "", "ENST00000269305", "protein_coding", false));
+    }
+
+    #[test]
+    fn transcript_unversioned() {
+        let f = EntryFilter {
+            transcripts: set(&["ENST00000380152"]),
+            ..filter()
+        };
+        assert!(f.matches("missense", "BRCA2", "", "ENST00000380152.8", "protein_coding", false));
+        assert!(f.matches("missense", "BRCA2", "", "ENST00000380152", "protein_coding", false));
+        assert!(!f.matches("missense", "BRCA2", "", "ENST00000544455.6", "protein_coding", false));
+    }
+
+    #[test]
+    fn subset_keeps_back_references() {
+        let entries = vec![
+            "missense|BRCA2|ENST00000380152|protein_coding|+|10A>T|32315508A>T".to_string(),
+            "@32315480".to_string(),
+            "intron|BRCA2|ENST00000544455|protein_coding|+".to_string(),
+        ];
+        let subset = bcsq_subset(&entries, |e| e.starts_with("missense"));
+        assert_eq!(subset, vec![entries[0].as_str(), "@32315480"]);
+        assert_eq!(bcsq_subset(&entries, |_| false), vec!["@32315480"]);
+    }
+}
@@ -0,0 +1,101 @@
+use rust_htslib::bcf::record::{Numeric, Record};
+use rust_htslib::bcf::Header;
+use rust_htslib::bgzf;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{prelude::*, BufReader};
+
+/// ClinGen dosage sensitivity scores, 0-3 for increasing evidence, 30 for autosomal
+/// recessive and 40 for dosage sensitivity unlikely
+#[derive(Debug, Default)]
+pub struct Clingen {
+    pub by_gene: HashMap<String, usize>,
+    scores: Vec<[Option<i32>; 2]>,
+}
+
+const CLINGEN_FIELDS: [(&str, &str, &str); 2] = [
+    ("clingen_hi", "Haploinsufficiency Score", "haploinsufficiency"),
+    ("clingen_ts", "Triplosensitivity Score", "triplosensitivity"),
+];
+
+impl Clingen {
+    /// the ClinGen_gene_curation_list download, plain or gzipped, its header is the
+    /// #Gene Symbol line after the comments
+    pub fn open(path: &str) -> Result<Clingen, Box<dyn Error>> {
+        let rdr = BufReader::new(bgzf::Reader::from_path(path)?);
+        let mut clingen = Clingen::default();
+        let mut idxs: Option<[usize; 3]> = None;
+        for line in rdr.lines() {
+            let l = line?;
+            let cols = l.split('\t').map(|c| c.trim()).collect::<Vec<&str>>();
+            let [gene, hi, ts] = match idxs {
+                Some(i) => i,
+                None => {
+                    let cols = cols.iter().map(|c| c.trim_start_matches('#')).collect::<Vec<&str>>();
+                    if cols[0] != "Gene Symbol" {
+                        continue;
+                    }
+                    let find = |name: &str| -> Result<usize, String> {
+                        return cols
+                            .iter()
+                            .position(|c| *c == name)
+                            .ok_or_else(|| format!("{} has no {} column", path, name));
+                    };
+                    idxs = Some([0, find(CLINGEN_FIELDS[0].1)?, find(CLINGEN_FIELDS[1].1)?]);
+                    continue;
+                }
+            };
+            let symbol = match cols.get(gene) {
+                Some(g) if !g.is_empty() => g.to_string(),
+                _ => continue,
+            };
+            // Not yet evaluated is left missing
+            let score = |i: usize| cols.get(i).and_then(|s| s.parse::<i32>().ok());
+            clingen.by_gene.insert(symbol, clingen.scores.len());
+            clingen.scores.push([score(hi), score(ts)]);
+        }
+        if idxs.is_none() {
+            return Err(format!("{} has no #Gene Symbol header line", path).into());
+        }
+        return Ok(clingen);
+    }
+
+    pub fn add_hdr_fields(&self, hdr: &mut Header, fields: &str, all_genes: bool) {
+        let number = match all_genes {
+            true => ".",
+            false => "1",
+        };
+        for (id, _, name) in CLINGEN_FIELDS {
+            hdr.push_record(format!("##INFO=<ID={},Number={},Type=Integer,Description=\"ClinGen {} score of the gene from {} INFO fields, 0-3 for increasing evidence, 30 for autosomal recessive and 40 for dosage sensitivity unlikely\">", id, number, name, fields).as_bytes());
+        }
+        return;
+    }
+
+    pub fn add_fields(&self, row: usize, record: &mut Record) {
+        for ((id, _, _), score) in CLINGEN_FIELDS.iter().zip(self.scores[row]) {
+            if let Some(s) = score {
+                record
+                    .push_info_integer(id.as_bytes(), &[s])
+                    .unwrap_or_else(|_| panic!("failed to set {} field", id));
+            }
+        }
+        return;
+    }
+
+    /// --all-genes, one score per row with the unscored ones missing
+    pub fn add_multi_fields(&self, rows: &Vec<usize>, record: &mut Record) {
+        for (i, (id, _, _)) in CLINGEN_FIELDS.iter().enumerate() {
+            let scores = rows.iter().map(|r| self.scores[*r][i]).collect::<Vec<Option<i32>>>();
+            if scores.iter().all(|s| s.is_none()) {
+                continue;
+            }
+            record
+                .push_info_integer(
+                    id.as_bytes(),
+                    &scores.iter().map(|s| s.unwrap_or_else(i32::missing)).collect::<Vec<i32>>(),
+                )
+                .unwrap_or_else(|_| panic!("failed to set {} field", id));
+        }
+        return;
+    }
+}
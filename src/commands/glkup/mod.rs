@@ -0,0 +1,826 @@
+use bcfutils::dbnsfp::{open_dbnsfp, read_dbnsfp, split_dbnsfp, DbnsfpKind, DbnsfpTable, DBNSFP_FIELDS};
+use bcfutils::genes::{resolve_gene, resolve_genes, unversioned, GeneModel};
+use bcfutils::open_table;
+use super::mcsq::{get_csq_format, get_csq_hdr_map, get_csq_layout, normalize_csq, schema};
+use rust_htslib::bcf::record::{Buffer, Numeric};
+use rust_htslib::bcf::{Header, Read};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::process;
+use std::str;
+use clingen::Clingen;
+use moi::Inheritance;
+use panel::Panels;
+use table::{escape_value, parse_columns, GeneTable};
+
+mod clingen;
+mod moi;
+mod panel;
+pub(crate) mod table;
+
+// lookups/ isn't checked in, building with --features embedded-tables compiles in a local copy
+#[cfg(feature = "embedded-tables")]
+static GNOMAD: Option<&[u8]> = Some(include_bytes!("../../../lookups/gnomad.v2.1.1.lof_metrics.by_gene.txt"));
+#[cfg(not(feature = "embedded-tables"))]
+static GNOMAD: Option<&[u8]> = None;
+
+#[derive(Debug, Deserialize)]
+struct Grow {
+    gene: String,
+    /// the v4 transcript the metrics are for, set by G4row::into_grow. v2.1.1 has a transcript
+    /// column too, its canonical transcript, but gnomAD_transcript is only declared for v4
+    #[serde(skip)]
+    transcript: Option<String>,
+    #[serde(rename = "pLI", deserialize_with = "csv::invalid_option")]
+    gnomAD_pLI: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    oe_lof: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    oe_lof_upper: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    syn_z: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    mis_z: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    lof_z: Option<f32>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    exac_pLI: Option<f32>,
+}
+
+/// gnomAD v4 constraint_metrics.tsv, one row per Ensembl and RefSeq transcript
+#[derive(Debug, Deserialize)]
+struct G4row {
+    gene: String,
+    transcript: String,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    canonical: Option<bool>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    mane_select: Option<bool>,
+    #[serde(rename = "lof.pLI", deserialize_with = "csv::invalid_option")]
+    lof_pli: Option<f32>,
+    #[serde(rename = "lof.oe", deserialize_with = "csv::invalid_option")]
+    lof_oe: Option<f32>,
+    #[serde(rename = "lof.oe_ci.upper", deserialize_with = "csv::invalid_option")]
+    lof_oe_ci_upper: Option<f32>,
+    #[serde(rename = "syn.z_score", deserialize_with = "csv::invalid_option")]
+    syn_z_score: Option<f32>,
+    #[serde(rename = "mis.z_score", deserialize_with = "csv::invalid_option")]
+    mis_z_score: Option<f32>,
+    #[serde(rename = "lof.z_score", deserialize_with = "csv::invalid_option")]
+    lof_z_score: Option<f32>,
+}
+
+impl G4row {
+    /// which of a gene's transcripts stands in for the gene, MANE Select then canonical
+    fn rank(&self) -> u8 {
+        return match (self.mane_select, self.canonical) {
+            (Some(true), _) => 0,
+            (_, Some(true)) => 1,
+            _ => 2,
+        };
+    }
+
+    fn into_grow(self) -> Grow {
+        return Grow {
+            gene: self.gene,
+            transcript: Some(self.transcript),
+            gnomAD_pLI: self.lof_pli,
+            oe_lof: self.lof_oe,
+            oe_lof_upper: self.lof_oe_ci_upper,
+            syn_z: self.syn_z_score,
+            mis_z: self.mis_z_score,
+            lof_z: self.lof_z_score,
+            exac_pLI: None,
+        };
+    }
+}
+
+/// gnomAD constraint rows by gene symbol, and by unversioned transcript for v4
+struct GnomadTable {
+    v4: bool,
+    rows: Vec<Grow>,
+    by_gene: HashMap<String, usize>,
+    by_transcript: HashMap<String, usize>,
+}
+
+/// INFO field, v2.1.1 lof_metrics.by_gene column, v4 constraint_metrics column
+const GNOMAD_FIELDS: [(&str, &str, Option<&str>); 7] = [
+    ("gnomAD_pLI", "pLI", Some("lof.pLI")),
+    ("oe_lof", "oe_lof", Some("lof.oe")),
+    ("oe_lof_upper", "oe_lof_upper", Some("lof.oe_ci.upper")),
+    ("syn_z", "syn_z", Some("syn.z_score")),
+    ("mis_z", "mis_z", Some("mis.z_score")),
+    ("lof_z", "lof_z", Some("lof.z_score")),
+    ("exac_pLI", "exac_pLI", None),
+];
+
+/// --all-genes summaries over a record's genes, INFO field, the GNOMAD_FIELDS field, max or min
+const GNOMAD_SUMMARIES: [(&str, &str, bool); 5] = [
+    ("max_pLI", "gnomAD_pLI", true),
+    ("min_oe_lof", "oe_lof", false),
+    ("min_LOEUF", "oe_lof_upper", false),
+    ("max_mis_z", "mis_z", true),
+    ("max_lof_z", "lof_z", true),
+];
+
+impl Grow {
+    /// a GNOMAD_FIELDS value by INFO field
+    fn get(&self, id: &str) -> Option<f32> {
+        return match id {
+            "gnomAD_pLI" => self.gnomAD_pLI,
+            "oe_lof" => self.oe_lof,
+            "oe_lof_upper" => self.oe_lof_upper,
+            "syn_z" => self.syn_z,
+            "mis_z" => self.mis_z,
+            "lof_z" => self.lof_z,
+            "exac_pLI" => self.exac_pLI,
+            _ => None,
+        };
+    }
+}
+
+/// mcsq picks looked up in v4 before falling back to the gene symbol
+const GNOMAD_TRANSCRIPT_FIELDS: [&str; 2] = ["mane_transcript", "canon_transcript"];
+
+/// v2.1.1 lof_metrics.by_gene or v4 constraint_metrics, told apart by the v4 lof.pLI column
+fn build_gnomad_map(table: Box<dyn io::Read>) -> GnomadTable {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .from_reader(table);
+    let v4 = match rdr.headers() {
+        Ok(h) => h.iter().any(|c| c == "lof.pLI"),
+        Err(e) => {
+            eprintln!("Error: unable to read gnomAD table header: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut gtab = GnomadTable {
+        v4,
+        rows: vec![],
+        by_gene: HashMap::new(),
+        by_transcript: HashMap::new(),
+    };
+    if !v4 {
+        for result in rdr.deserialize() {
+            match result {
+                Ok::<Grow, _>(grow) => {
+                    gtab.by_gene.insert(grow.gene.clone(), gtab.rows.len());
+                    gtab.rows.push(grow);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "serde had serializing gnomad, check columns match code: {}",
+                        e
+                    );
+                    process::exit(1);
+                }
+            }
+        }
+        return gtab;
+    }
+
+    let mut gene_ranks: HashMap<String, u8> = HashMap::new();
+    for result in rdr.deserialize() {
+        let g4row: G4row = match result {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!(
+                    "serde had serializing gnomad v4, check columns match code: {}",
+                    e
+                );
+                process::exit(1);
+            }
+        };
+        let idx = gtab.rows.len();
+        let rank = g4row.rank();
+        if gene_ranks.get(&g4row.gene).is_none_or(|r| rank < *r) {
+            gene_ranks.insert(g4row.gene.clone(), rank);
+            gtab.by_gene.insert(g4row.gene.clone(), idx);
+        }
+        gtab.by_transcript
+            .insert(unversioned(&g4row.transcript).to_string(), idx);
+        gtab.rows.push(g4row.into_grow());
+    }
+    return gtab;
+}
+
+fn add_gnomad_hdr_fields(hdr: &mut rust_htslib::bcf::Header, fields: &str, v4: bool, all_genes: bool) {
+    let number = match all_genes {
+        true => ".",
+        false => "1",
+    };
+    hdr.push_record(format!("##INFO=<ID=gnomAD_gene,Number={},Type=String,Description=\"gene for gnomad gene level fields, from {} INFO fields\">", number, fields).as_bytes());
+    if v4 {
+        hdr.push_record(format!("##INFO=<ID=gnomAD_transcript,Number={},Type=String,Description=\"transcript for gnomad v4 constraint fields, from INFO.{} or the MANE Select/canonical transcript of INFO.gnomAD_gene\">", number, GNOMAD_TRANSCRIPT_FIELDS.join("/INFO.")).as_bytes());
+    }
+    for (id, v2_col, v4_col) in GNOMAD_FIELDS {
+        let desc = match (v4, v4_col) {
+            (false, _) => format!("{} from gnomad.v2.1.1.lof_metrics.by_gene.txt, using INFO.gnomAD_gene to lookup", v2_col),
+            (true, Some(c)) => format!("{} from gnomad.v4 constraint_metrics.tsv, using INFO.gnomAD_transcript to lookup", c),
+            (true, None) => continue,
+        };
+        hdr.push_record(format!("##INFO=<ID={},Number={},Type=Float,Description=\"{}\">", id, number, desc).as_bytes());
+    }
+    if all_genes {
+        for (id, field, max) in GNOMAD_SUMMARIES {
+            let agg = match max {
+                true => "max",
+                false => "min",
+            };
+            hdr.push_record(format!("##INFO=<ID={},Number=1,Type=Float,Description=\"{} of INFO.{} over the genes of the variant\">", id, agg, field).as_bytes());
+        }
+    }
+    return;
+}
+
+/// lists are Number=. per item, with --all-genes they are one value per gene with the items
+/// joined by &
+fn add_dbnsfp_hdr_fields(hdr: &mut rust_htslib::bcf::Header, fields: &str, all_genes: bool, sep: &str) {
+    let number = match all_genes {
+        true => ".",
+        false => "1",
+    };
+    hdr.push_record(format!("##INFO=<ID=dbnsfp_gene,Number={},Type=String,Description=\"gene for dbnsfp gene level fields, from {}\">", number, fields).as_bytes());
+    for (id, col, kind) in DBNSFP_FIELDS {
+        let (number, typ, split) = match (kind, all_genes) {
+            (DbnsfpKind::Text, _) => (number, "String", String::new()),
+            (_, true) => (".", "String", format!(", split on {} and joined by &", sep)),
+            (DbnsfpKind::List, false) => (".", "String", format!(", split on {}", sep)),
+            (DbnsfpKind::IntList, false) => (".", "Integer", format!(", split on {}", sep)),
+        };
+        hdr.push_record(format!("##INFO=<ID={},Number={},Type={},Description=\"{} from dbNSFP4.2_gene.complete using INFO.dbnsfp_gene to lookup{}\">", id, number, typ, col, split).as_bytes());
+    }
+    return;
+}
+
+fn add_gnomad_fields(grow: &Grow, record: &mut rust_htslib::bcf::record::Record) {
+    record
+        .push_info_string("gnomAD_gene".as_bytes(), &[grow.gene.as_bytes()])
+        .expect("failed to set gnomAD_gene field");
+    if let Some(transcript) = grow.transcript.as_ref() {
+        record
+            .push_info_string("gnomAD_transcript".as_bytes(), &[transcript.as_bytes()])
+            .expect("failed to set gnomAD_transcript field");
+    }
+    match grow.gnomAD_pLI {
+        Some(gnomad_pli) => {
+            record
+                .push_info_float("gnomAD_pLI".as_bytes(), &[gnomad_pli])
+                .expect("failed to set gnomAD_pLI field");
+        }
+        None => (),
+    };
+
+    match grow.oe_lof {
+        Some(oe_lof) => {
+            record
+                .push_info_float("oe_lof".as_bytes(), &[oe_lof])
+                .expect("failed to set oe_lof field");
+        }
+        None => (),
+    };
+    match grow.oe_lof_upper {
+        Some(oe_lof_upper) => {
+            record
+                .push_info_float("oe_lof_upper".as_bytes(), &[oe_lof_upper])
+                .expect("failed to set oe_lof_upper field");
+        }
+        None => (),
+    };
+    match grow.syn_z {
+        Some(syn_z) => {
+            record
+                .push_info_float("syn_z".as_bytes(), &[syn_z])
+                .expect("failed to set syn_z field");
+        }
+        None => (),
+    };
+    match grow.mis_z {
+        Some(mis_z) => {
+            record
+                .push_info_float("mis_z".as_bytes(), &[mis_z])
+                .expect("failed to set mis_z field");
+        }
+        None => (),
+    };
+    match grow.lof_z {
+        Some(lof_z) => {
+            record
+                .push_info_float("lof_z".as_bytes(), &[lof_z])
+                .expect("failed to set lof_z field");
+        }
+        None => (),
+    };
+    match grow.exac_pLI {
+        Some(exac_pli) => {
+            record
+                .push_info_float("exac_pLI".as_bytes(), &[exac_pli])
+                .expect("failed to set exac_pLI field");
+        }
+        None => (),
+    };
+    return;
+}
+
+fn add_dbnsfp_fields(dtab: &DbnsfpTable, row: usize, sep: &str, record: &mut rust_htslib::bcf::record::Record) {
+    record
+        .push_info_string("dbnsfp_gene".as_bytes(), &[dtab.genes[row].as_bytes()])
+        .expect("failed to set dbnsfp_gene field");
+    for ((id, _, kind), val) in DBNSFP_FIELDS.iter().zip(dtab.values(row)) {
+        let res = match kind {
+            DbnsfpKind::Text => match val {
+                "" | "." => continue,
+                v => record.push_info_string(id.as_bytes(), &[escape_value(v).as_bytes()]),
+            },
+            DbnsfpKind::List => {
+                let items = split_dbnsfp(val, sep)
+                    .iter()
+                    .map(|v| escape_value(v))
+                    .collect::<Vec<String>>();
+                if items.is_empty() {
+                    continue;
+                }
+                record.push_info_string(
+                    id.as_bytes(),
+                    &items.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>(),
+                )
+            }
+            DbnsfpKind::IntList => {
+                let items = split_dbnsfp(val, sep)
+                    .iter()
+                    .filter_map(|v| v.parse::<i32>().ok())
+                    .collect::<Vec<i32>>();
+                if items.is_empty() {
+                    continue;
+                }
+                record.push_info_integer(id.as_bytes(), &items)
+            }
+        };
+        res.unwrap_or_else(|_| panic!("failed to set {} field", id));
+    }
+    return;
+}
+
+/// --all-genes, one value per gene in the order of grows, missing ones written as .
+fn add_gnomad_multi_fields(grows: &Vec<&Grow>, record: &mut rust_htslib::bcf::record::Record) {
+    let genes = grows.iter().map(|g| g.gene.as_bytes()).collect::<Vec<&[u8]>>();
+    record
+        .push_info_string("gnomAD_gene".as_bytes(), &genes)
+        .expect("failed to set gnomAD_gene field");
+    if grows.iter().any(|g| g.transcript.is_some()) {
+        let transcripts = grows
+            .iter()
+            .map(|g| g.transcript.as_deref().unwrap_or(".").as_bytes())
+            .collect::<Vec<&[u8]>>();
+        record
+            .push_info_string("gnomAD_transcript".as_bytes(), &transcripts)
+            .expect("failed to set gnomAD_transcript field");
+    }
+    for (id, _, _) in GNOMAD_FIELDS {
+        let vals = grows.iter().map(|g| g.get(id)).collect::<Vec<Option<f32>>>();
+        if vals.iter().all(|v| v.is_none()) {
+            continue;
+        }
+        let vals = vals
+            .iter()
+            .map(|v| v.unwrap_or_else(f32::missing))
+            .collect::<Vec<f32>>();
+        record
+            .push_info_float(id.as_bytes(), &vals)
+            .unwrap_or_else(|_| panic!("failed to set {} field", id));
+    }
+    for (id, field, max) in GNOMAD_SUMMARIES {
+        let vals = grows.iter().filter_map(|g| g.get(field));
+        let summary = match max {
+            true => vals.reduce(f32::max),
+            false => vals.reduce(f32::min),
+        };
+        if let Some(v) = summary {
+            record
+                .push_info_float(id.as_bytes(), &[v])
+                .unwrap_or_else(|_| panic!("failed to set {} field", id));
+        }
+    }
+    return;
+}
+
+fn add_dbnsfp_multi_fields(
+    dtab: &DbnsfpTable,
+    rows: &Vec<usize>,
+    sep: &str,
+    record: &mut rust_htslib::bcf::record::Record,
+) {
+    let genes = rows.iter().map(|r| dtab.genes[*r].as_bytes()).collect::<Vec<&[u8]>>();
+    record
+        .push_info_string("dbnsfp_gene".as_bytes(), &genes)
+        .expect("failed to set dbnsfp_gene field");
+    let values = rows.iter().map(|r| dtab.values(*r)).collect::<Vec<Vec<&str>>>();
+    for (i, (id, _, kind)) in DBNSFP_FIELDS.iter().enumerate() {
+        let vals = values
+            .iter()
+            .map(|v| match kind {
+                DbnsfpKind::Text => match v[i] {
+                    "" | "." => ".".to_string(),
+                    t => escape_value(t),
+                },
+                _ => match split_dbnsfp(v[i], sep)
+                    .into_iter()
+                    .filter(|t| *kind == DbnsfpKind::List || t.parse::<i32>().is_ok())
+                    .collect::<Vec<&str>>()
+                {
+                    items if items.is_empty() => ".".to_string(),
+                    items => items
+                        .iter()
+                        .map(|t| escape_value(t))
+                        .collect::<Vec<String>>()
+                        .join("&"),
+                },
+            })
+            .collect::<Vec<String>>();
+        if vals.iter().all(|v| v == ".") {
+            continue;
+        }
+        record
+            .push_info_string(id.as_bytes(), &vals.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>())
+            .unwrap_or_else(|_| panic!("failed to set {} field", id));
+    }
+    return;
+}
+
+/// v4 rows of the mcsq picked transcripts, MANE before canonical and one per gene
+fn get_gnomad_transcript_rows(
+    record: &rust_htslib::bcf::Record,
+    gtab: &GnomadTable,
+    b: &mut Buffer,
+) -> Vec<usize> {
+    let mut rows: Vec<usize> = vec![];
+    if !gtab.v4 {
+        return rows;
+    }
+    for f in GNOMAD_TRANSCRIPT_FIELDS {
+        if let Ok(Some(vals)) = record.info_shared_buffer(f.as_bytes(), &mut *b).string() {
+            for v in vals.iter() {
+                let t = unversioned(str::from_utf8(v).unwrap());
+                if let Some(&i) = gtab.by_transcript.get(t) {
+                    if !rows.iter().any(|r| gtab.rows[*r].gene == gtab.rows[i].gene) {
+                        rows.push(i);
+                    }
+                }
+            }
+        }
+    }
+    return rows;
+}
+
+/// every value of the --fields tags then the gene of every csq entry, distinct and in order of
+/// first appearance so the Number=. fields line up from one record to the next
+fn get_all_genes(
+    record: &rust_htslib::bcf::Record,
+    fs: &Vec<&str>,
+    csq: Option<&(String, Vec<Option<usize>>)>,
+    b: &mut Buffer,
+) -> Vec<String> {
+    let mut genes: Vec<String> = vec![];
+    let mut push = |g: &str| {
+        if !g.is_empty() && g != "." && !genes.iter().any(|x| x == g) {
+            genes.push(g.to_string());
+        }
+    };
+    for f in fs {
+        if let Ok(Some(vals)) = record.info_shared_buffer(f.as_bytes(), &mut *b).string() {
+            for v in vals.iter() {
+                push(str::from_utf8(v).unwrap());
+            }
+        }
+    }
+    if let Some((tag, layout)) = csq {
+        if let Ok(Some(entries)) = record.info_shared_buffer(tag.as_bytes(), b).string() {
+            for e in entries.iter() {
+                let e = str::from_utf8(e).unwrap();
+                if e.starts_with('@') {
+                    continue;
+                }
+                let norm = normalize_csq(e, layout);
+                if let Some(g) = norm.split('|').nth(schema::GENE) {
+                    push(g);
+                }
+            }
+        }
+    }
+    return genes;
+}
+
+/// the i-th value of a per --table (or --source) option, given once it applies to every one
+pub(crate) fn get_table_arg<'a>(vals: &'a Vec<String>, i: usize, n: usize, name: &str, per: &str) -> Option<&'a str> {
+    return match vals.len() {
+        0 => None,
+        1 => Some(vals[0].as_str()),
+        l if l == n => Some(vals[i].as_str()),
+        l => {
+            eprintln!("Error: {} --{} for {} --{}, give it once or once per {}", l, name, n, per, per);
+            process::exit(1);
+        }
+    };
+}
+
+fn open_gene_tables(
+    tables: &Vec<String>,
+    key_columns: &Vec<String>,
+    columns: &Vec<String>,
+    prefixes: &Vec<String>,
+) -> Vec<GeneTable> {
+    let n = tables.len();
+    let mut ids = HashSet::new();
+    let mut gene_tables = vec![];
+    for (i, path) in tables.iter().enumerate() {
+        let spec = match get_table_arg(columns, i, n, "columns", "table") {
+            Some(c) => c,
+            None => {
+                eprintln!("Error: --table {} needs --columns name:Type,...", path);
+                process::exit(1);
+            }
+        };
+        let key = get_table_arg(key_columns, i, n, "key-column", "table").unwrap_or("gene");
+        let prefix = get_table_arg(prefixes, i, n, "prefix", "table").unwrap_or("");
+        let cols = parse_columns(spec, prefix).unwrap_or_else(|e| {
+            eprintln!("Error: bad --columns for {}: {}", path, e);
+            process::exit(1);
+        });
+        for c in &cols {
+            if !ids.insert(c.id.clone()) {
+                eprintln!("Error: INFO field {} comes from more than one --table, give them a --prefix", c.id);
+                process::exit(1);
+            }
+        }
+        match GeneTable::open(path, key, cols) {
+            Ok(t) => gene_tables.push(t),
+            Err(e) => {
+                eprintln!("Error: unable to read gene table {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    return gene_tables;
+}
+
+pub fn glkup(
+    input: Option<&str>,
+    output: Option<&str>,
+    fields: Option<&str>,
+    gff: Option<&str>,
+    dbnsfp: &bool,
+    gnomad_table: Option<&str>,
+    dbnsfp_table: Option<&str>,
+    dbnsfp_sep: &str,
+    tables: &Vec<String>,
+    key_columns: &Vec<String>,
+    columns: &Vec<String>,
+    prefixes: &Vec<String>,
+    all_genes: &bool,
+    panels: &Vec<String>,
+    panel_only: &bool,
+    clingen: Option<&str>,
+    moi: &Vec<String>,
+    threads: &usize,
+) {
+    let mut bcf = bcfutils::get_rdr(input);
+    bcf.set_threads(threads.clone())
+        .expect("unable to set reader threads");
+
+    let hdrv = bcf.header();
+    let mut hdr = Header::from_template(&hdrv);
+
+    // --all-genes also takes the genes of the BCSQ/CSQ/ANN entries
+    let csq = match all_genes {
+        true => get_csq_hdr_map(hdrv.header_records()).map(|(tag, csq_map)| {
+            let format = get_csq_format(csq_map.get("Description").expect("csq map doesnt have \"Description\""));
+            (tag, get_csq_layout(&format))
+        }),
+        false => None,
+    };
+    let fields = match (fields, csq.as_ref()) {
+        (Some(f), _) => f,
+        (None, Some((tag, _))) => tag.as_str(),
+        (None, None) => {
+            eprintln!("Error: need to specify INFO fields to get gene name from");
+            process::exit(1);
+        }
+    };
+
+    let gene_tables = open_gene_tables(tables, key_columns, columns, prefixes);
+    let panels = match Panels::open(panels) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: unable to read gene panel: {}", e);
+            process::exit(1);
+        }
+    };
+    if *panel_only && panels.is_empty() {
+        eprintln!("Error: --panel-only needs at least one --panel");
+        process::exit(1);
+    }
+    let clingen = clingen.map(|fp| match Clingen::open(fp) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: unable to read ClinGen table {}: {}", fp, e);
+            process::exit(1);
+        }
+    });
+    let inheritance = match Inheritance::open(moi) {
+        Ok(i) => i,
+        Err(e) => {
+            eprintln!("Error: unable to read inheritance table: {}", e);
+            process::exit(1);
+        }
+    };
+    // gnomAD is optional when there are other lookups to do instead
+    let other_lookups = !gene_tables.is_empty() || !panels.is_empty() || clingen.is_some() || !moi.is_empty();
+    let gmap = match open_table(gnomad_table, GNOMAD) {
+        Some(t) => Some(build_gnomad_map(t)),
+        None if !other_lookups => {
+            eprintln!("Error: no gnomAD table, pass --gnomad-table or build with --features embedded-tables");
+            process::exit(1);
+        }
+        None => None,
+    };
+    if let Some(gtab) = gmap.as_ref() {
+        add_gnomad_hdr_fields(&mut hdr, fields, gtab.v4, *all_genes);
+    }
+
+    // without --dbnsfp the table is only read for its synonyms, when there is one
+    let (dtab, syns) = match open_dbnsfp(dbnsfp_table) {
+        Some(t) => read_dbnsfp(t, *dbnsfp),
+        None if *dbnsfp => {
+            eprintln!("Error: no dbNSFP gene table, pass --dbnsfp-table or build with --features embedded-tables");
+            process::exit(1);
+        }
+        None => (DbnsfpTable::default(), HashMap::new()),
+    };
+    let model = gff.map(|fp| match GeneModel::from_gff(fp) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Error: unable to read gff file {}: {}", fp, e);
+            process::exit(1);
+        }
+    });
+    if *dbnsfp {
+        add_dbnsfp_hdr_fields(&mut hdr, fields, *all_genes, dbnsfp_sep);
+    };
+    for t in &gene_tables {
+        t.add_hdr_fields(&mut hdr, fields, *all_genes);
+    }
+    if !panels.is_empty() {
+        panels.add_hdr_fields(&mut hdr, fields);
+    }
+    if let Some(c) = clingen.as_ref() {
+        c.add_hdr_fields(&mut hdr, fields, *all_genes);
+    }
+    if !moi.is_empty() {
+        inheritance.add_hdr_fields(&mut hdr, fields, *all_genes);
+    }
+
+    let mut obcf = bcfutils::get_wrtr(output, &hdr);
+    obcf.set_threads(threads.clone())
+        .expect("unable to set writer threads");
+
+    // with only a csq tag to go on there are no --fields tags to read
+    let fs: Vec<&str> = match csq.as_ref() {
+        Some((tag, _)) if tag == fields => vec![],
+        _ => fields.split(",").collect(),
+    };
+
+    let mut b = Buffer::new();
+    for record_result in bcf.records() {
+        let mut record = record_result.expect("fail to read record");
+        obcf.translate(&mut record);
+
+        // the first of the INFO fields, in the order given, whose gene is in a table wins
+        let genes = match all_genes {
+            true => get_all_genes(&record, &fs, csq.as_ref(), &mut b),
+            false => {
+                let mut genes = vec![];
+                for f in &fs {
+                    if let Ok(Some(g)) = record.info_shared_buffer(f.as_bytes(), &mut b).string() {
+                        genes.push(str::from_utf8(g[0]).unwrap().to_string());
+                    }
+                }
+                genes
+            }
+        };
+
+        if !panels.is_empty() {
+            let rows = match all_genes {
+                true => resolve_genes(&genes, &panels.by_gene, model.as_ref(), &syns),
+                false => genes
+                    .iter()
+                    .find_map(|g| resolve_gene(g, &panels.by_gene, model.as_ref(), &syns))
+                    .into_iter()
+                    .copied()
+                    .collect(),
+            };
+            if rows.is_empty() && *panel_only {
+                continue;
+            }
+            if !rows.is_empty() {
+                panels.add_fields(&rows, &mut record);
+            }
+        }
+
+        if *all_genes {
+            if let Some(gtab) = gmap.as_ref() {
+                // a gene's picked transcript stands in for its MANE Select/canonical row
+                let picked = get_gnomad_transcript_rows(&record, gtab, &mut b);
+                let same_gene = |r: usize, p: usize| gtab.rows[r].gene == gtab.rows[p].gene;
+                let mut rows = resolve_genes(&genes, &gtab.by_gene, model.as_ref(), &syns)
+                    .iter()
+                    .map(|r| *picked.iter().find(|p| same_gene(*r, **p)).unwrap_or(r))
+                    .collect::<Vec<usize>>();
+                for p in picked {
+                    if !rows.iter().any(|r| same_gene(*r, p)) {
+                        rows.push(p);
+                    }
+                }
+                let grows = rows
+                    .iter()
+                    .map(|i| &gtab.rows[*i])
+                    .collect::<Vec<&Grow>>();
+                if !grows.is_empty() {
+                    add_gnomad_multi_fields(&grows, &mut record);
+                }
+            }
+            if *dbnsfp {
+                let rows = resolve_genes(&genes, &dtab.by_gene, model.as_ref(), &syns);
+                if !rows.is_empty() {
+                    add_dbnsfp_multi_fields(&dtab, &rows, dbnsfp_sep, &mut record);
+                }
+            }
+            for t in &gene_tables {
+                let rows = resolve_genes(&genes, &t.by_gene, model.as_ref(), &syns);
+                if !rows.is_empty() {
+                    t.add_multi_fields(&rows, &mut record);
+                }
+            }
+            if let Some(c) = clingen.as_ref() {
+                let rows = resolve_genes(&genes, &c.by_gene, model.as_ref(), &syns);
+                if !rows.is_empty() {
+                    c.add_multi_fields(&rows, &mut record);
+                }
+            }
+            if !inheritance.is_empty() {
+                let rows = resolve_genes(&genes, &inheritance.by_gene, model.as_ref(), &syns);
+                if !rows.is_empty() {
+                    inheritance.add_multi_fields(&rows, &mut record);
+                }
+            }
+            obcf.write(&record).expect("failed to write record");
+            continue;
+        }
+
+        if let Some(gtab) = gmap.as_ref() {
+            let idx = get_gnomad_transcript_rows(&record, gtab, &mut b).first().copied();
+            let idx = idx.or_else(|| {
+                genes
+                    .iter()
+                    .find_map(|g| resolve_gene(g, &gtab.by_gene, model.as_ref(), &syns))
+                    .copied()
+            });
+            if let Some(i) = idx {
+                add_gnomad_fields(&gtab.rows[i], &mut record);
+            }
+        }
+
+        if *dbnsfp {
+            if let Some(row) = genes
+                .iter()
+                .find_map(|g| resolve_gene(g, &dtab.by_gene, model.as_ref(), &syns))
+            {
+                add_dbnsfp_fields(&dtab, *row, dbnsfp_sep, &mut record);
+            }
+        };
+
+        for t in &gene_tables {
+            if let Some(row) = genes
+                .iter()
+                .find_map(|g| resolve_gene(g, &t.by_gene, model.as_ref(), &syns))
+            {
+                t.add_fields(*row, &mut record);
+            }
+        }
+        if let Some(c) = clingen.as_ref() {
+            if let Some(row) = genes
+                .iter()
+                .find_map(|g| resolve_gene(g, &c.by_gene, model.as_ref(), &syns))
+            {
+                c.add_fields(*row, &mut record);
+            }
+        }
+        if let Some(row) = genes
+            .iter()
+            .find_map(|g| resolve_gene(g, &inheritance.by_gene, model.as_ref(), &syns))
+        {
+            inheritance.add_fields(*row, &mut record);
+        }
+        obcf.write(&record).expect("failed to write record");
+    }
+}
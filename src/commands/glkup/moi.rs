@@ -0,0 +1,254 @@
+use rust_htslib::bcf::record::Record;
+use rust_htslib::bcf::Header;
+use rust_htslib::bgzf;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, prelude::*, BufReader};
+
+/// modes of inheritance, written in this order
+const MODES: [&str; 8] = ["AD", "AR", "XLD", "XLR", "XL", "YL", "MT", "SD"];
+
+/// GenCC classifications too weak to take the gene's mode of inheritance from
+const GENCC_SKIP: [&str; 4] = [
+    "Limited",
+    "Disputed Evidence",
+    "Refuted Evidence",
+    "No Known Disease Relationship",
+];
+
+/// OMIM writes Autosomal dominant, GenCC Autosomal dominant or Autosomal dominant inheritance
+fn parse_mode(moi: &str) -> Option<usize> {
+    let moi = moi.trim().to_ascii_lowercase();
+    let moi = moi
+        .trim_end_matches(" mode of inheritance")
+        .trim_end_matches(" inheritance");
+    let mode = match moi {
+        "autosomal dominant" | "pseudoautosomal dominant" => "AD",
+        "autosomal recessive" | "pseudoautosomal recessive" => "AR",
+        "x-linked dominant" => "XLD",
+        "x-linked recessive" => "XLR",
+        "x-linked" => "XL",
+        "y-linked" => "YL",
+        "mitochondrial" => "MT",
+        "semidominant" => "SD",
+        _ => return None,
+    };
+    return MODES.iter().position(|m| *m == mode);
+}
+
+/// OMIM and GenCC modes of inheritance, a bit per MODES entry for each gene
+#[derive(Debug, Default)]
+pub struct Inheritance {
+    pub by_gene: HashMap<String, usize>,
+    modes: Vec<u8>,
+}
+
+impl Inheritance {
+    /// each an OMIM genemap2.txt or GenCC submissions TSV, plain or gzipped
+    pub fn open(paths: &Vec<String>) -> Result<Inheritance, Box<dyn Error>> {
+        let mut inh = Inheritance::default();
+        for path in paths {
+            inh.read(path)?;
+        }
+        return Ok(inh);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.modes.is_empty();
+    }
+
+    /// told apart by the header, GenCC's is its first line
+    fn read(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut rdr = BufReader::new(bgzf::Reader::from_path(path)?);
+        let mut first = String::new();
+        rdr.read_line(&mut first)?;
+        let text = io::Cursor::new(first.clone()).chain(rdr);
+        if first.split('\t').any(|c| c.trim().trim_matches('"') == "moi_title") {
+            return self.read_gencc(text);
+        }
+        if !self.read_omim(BufReader::new(text))? {
+            return Err(format!("{} is neither an OMIM genemap2 nor a GenCC submissions file", path).into());
+        }
+        return Ok(());
+    }
+
+    /// false when there was no genemap2 header
+    fn read_omim(&mut self, rdr: impl BufRead) -> Result<bool, Box<dyn Error>> {
+        let mut names: Option<Vec<String>> = None;
+        for line in rdr.lines() {
+            let l = line?;
+            let cols = l.split('\t').map(|c| c.trim()).collect::<Vec<&str>>();
+            let names = match names.as_ref() {
+                Some(n) => n,
+                None => {
+                    let hdr = cols
+                        .iter()
+                        .map(|c| c.trim_start_matches('#').trim().to_string())
+                        .collect::<Vec<String>>();
+                    if hdr.iter().any(|n| n == "Phenotypes") {
+                        names = Some(hdr);
+                    }
+                    continue;
+                }
+            };
+            if l.starts_with('#') {
+                continue;
+            }
+            let get = |name: &str| -> &str {
+                return names
+                    .iter()
+                    .position(|n| n == name)
+                    .and_then(|i| cols.get(i).copied())
+                    .unwrap_or("");
+            };
+            let symbol = match get("Approved Gene Symbol") {
+                "" => get("Approved Symbol"),
+                s => s,
+            };
+            let keys = [symbol, get("Ensembl Gene ID")];
+            // Epilepsy, 612164 (3), Autosomal dominant, Autosomal recessive; ...
+            for p in get("Phenotypes").split(';') {
+                let modes = p.rfind(')').map_or("", |i| &p[i + 1..]);
+                for m in modes.split(',').filter_map(parse_mode) {
+                    self.add(&keys, m);
+                }
+            }
+        }
+        return Ok(names.is_some());
+    }
+
+    /// GenCC quotes every value, and the free text ones can hold tabs and newlines
+    fn read_gencc(&mut self, text: impl io::Read) -> Result<(), Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .quoting(true)
+            .flexible(true)
+            .from_reader(text);
+        let names = rdr.headers()?.clone();
+        let col = |name: &str| names.iter().position(|n| n.trim() == name);
+        let (classification, moi) = (col("classification_title"), col("moi_title"));
+        let (symbol, curie) = (col("gene_symbol"), col("gene_curie"));
+        for result in rdr.records() {
+            let row = result?;
+            let get = |i: Option<usize>| i.and_then(|i| row.get(i)).map_or("", |v| v.trim());
+            if GENCC_SKIP.contains(&get(classification)) {
+                continue;
+            }
+            if let Some(m) = parse_mode(get(moi)) {
+                self.add(&[get(symbol), get(curie)], m);
+            }
+        }
+        return Ok(());
+    }
+
+    /// the symbol and ID of a gene share its row
+    fn add(&mut self, keys: &[&str], mode: usize) {
+        let keys = keys.iter().filter(|k| !k.is_empty()).collect::<Vec<&&str>>();
+        if keys.is_empty() {
+            return;
+        }
+        let row = match keys.iter().find_map(|k| self.by_gene.get(**k)) {
+            Some(r) => *r,
+            None => {
+                self.modes.push(0);
+                self.modes.len() - 1
+            }
+        };
+        for k in keys {
+            self.by_gene.entry(k.to_string()).or_insert(row);
+        }
+        self.modes[row] |= 1 << mode;
+    }
+
+    fn modes(&self, row: usize) -> Vec<&'static str> {
+        return MODES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.modes[row] & (1 << i) != 0)
+            .map(|(_, m)| *m)
+            .collect();
+    }
+
+    pub fn add_hdr_fields(&self, hdr: &mut Header, fields: &str, all_genes: bool) {
+        let desc = match all_genes {
+            true => "one value per gene with & between the modes",
+            false => "one value per mode",
+        };
+        hdr.push_record(format!("##INFO=<ID=moi,Number=.,Type=String,Description=\"OMIM/GenCC modes of inheritance of the gene from {} INFO fields, of {}, {}\">", fields, MODES.join("/"), desc).as_bytes());
+        return;
+    }
+
+    pub fn add_fields(&self, row: usize, record: &mut Record) {
+        let modes = self.modes(row);
+        record
+            .push_info_string(b"moi", &modes.iter().map(|m| m.as_bytes()).collect::<Vec<&[u8]>>())
+            .expect("failed to set moi field");
+        return;
+    }
+
+    /// --all-genes, the modes of each row joined by &
+    pub fn add_multi_fields(&self, rows: &Vec<usize>, record: &mut Record) {
+        let vals = rows
+            .iter()
+            .map(|r| self.modes(*r).join("&"))
+            .collect::<Vec<String>>();
+        record
+            .push_info_string(b"moi", &vals.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>())
+            .expect("failed to set moi field");
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// an inheritance file in the temp dir, read then removed
+    fn read(name: &str, text: &str) -> Inheritance {
+        let fp = std::env::temp_dir().join(format!("bcfutils_{}_{}.tsv", name, std::process::id()));
+        fs::write(&fp, text).unwrap();
+        let inh = Inheritance::open(&vec![fp.display().to_string()]);
+        fs::remove_file(&fp).unwrap();
+        return inh.unwrap();
+    }
+
+    #[test]
+    fn gencc_quoted_values() {
+        let inh = read(
+            "gencc",
+            concat!(
+                "\"uuid\"\t\"gene_curie\"\t\"gene_symbol\"\t\"classification_title\"\t\"moi_title\"\t\"submitted_as_notes\"\n",
+                "\"1\"\t\"HGNC:14825\"\t\"OR4F5\"\t\"Definitive\"\t\"Autosomal recessive\"\t\"a note\twith a tab\nand a newline\"\n",
+                "\"2\"\t\"HGNC:14825\"\t\"OR4F5\"\t\"Refuted Evidence\"\t\"Autosomal dominant\"\t\"\"\n",
+                "\"3\"\t\"HGNC:1100\"\t\"BRCA1\"\t\"Strong\"\t\"Autosomal dominant inheritance\"\t\"said \"\"AD\"\"\"\n",
+            ),
+        );
+        assert_eq!(inh.modes(inh.by_gene["OR4F5"]), vec!["AR"]);
+        assert_eq!(inh.by_gene["HGNC:14825"], inh.by_gene["OR4F5"]);
+        assert_eq!(inh.modes(inh.by_gene["BRCA1"]), vec!["AD"]);
+    }
+
+    #[test]
+    fn omim_genemap2() {
+        let inh = read(
+            "genemap2",
+            concat!(
+                "# Copyright\n",
+                "# Chromosome\tMIM Number\tApproved Gene Symbol\tEnsembl Gene ID\tPhenotypes\n",
+                "chr1\t1\tGENEA\tENSG00000000001\tDeafness, autosomal dominant 1, 124900 (3), Autosomal dominant; Epilepsy, 1 (3), Autosomal recessive\n",
+                "# Phenotype Mapping key\n",
+            ),
+        );
+        assert_eq!(inh.modes(inh.by_gene["ENSG00000000001"]), vec!["AD", "AR"]);
+    }
+
+    #[test]
+    fn neither() {
+        let fp = std::env::temp_dir().join(format!("bcfutils_neither_{}.tsv", std::process::id()));
+        fs::write(&fp, "gene\tmoi\nGENEA\tAD\n").unwrap();
+        let inh = Inheritance::open(&vec![fp.display().to_string()]);
+        fs::remove_file(&fp).unwrap();
+        assert!(inh.is_err());
+    }
+}
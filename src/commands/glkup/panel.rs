@@ -0,0 +1,215 @@
+use super::table::escape_value;
+use rust_htslib::bcf::record::Record;
+use rust_htslib::bcf::Header;
+use rust_htslib::bgzf;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{prelude::*, BufReader};
+use std::path::Path;
+
+/// a gene's place on one panel
+#[derive(Debug)]
+pub struct PanelEntry {
+    pub panel: usize,
+    pub gene: String,
+    pub confidence: String,
+    pub moi: String,
+}
+
+/// gene panels, a by_gene row holds every panel the gene is on
+#[derive(Debug, Default)]
+pub struct Panels {
+    names: Vec<String>,
+    pub by_gene: HashMap<String, usize>,
+    entries: Vec<Vec<PanelEntry>>,
+}
+
+/// PanelApp GEL_Status, 3 and up is green
+fn panelapp_confidence(status: &str) -> String {
+    return match status {
+        "3" | "4" => "green".to_string(),
+        "2" => "amber".to_string(),
+        "0" | "1" => "red".to_string(),
+        s => s.to_string(),
+    };
+}
+
+impl Panels {
+    /// each arg a PanelApp TSV or a gene list, optionally named with name=path
+    pub fn open(args: &Vec<String>) -> Result<Panels, Box<dyn Error>> {
+        let mut panels = Panels::default();
+        for arg in args {
+            let (name, path) = match arg.split_once('=') {
+                Some((n, p)) if !Path::new(arg).exists() => (Some(n), p),
+                _ => (None, arg.as_str()),
+            };
+            panels.read_panel(name, path)?;
+        }
+        return Ok(panels);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.names.is_empty();
+    }
+
+    /// the file name without its extensions names a panel that isn't given one
+    fn read_panel(&mut self, name: Option<&str>, path: &str) -> Result<(), Box<dyn Error>> {
+        let stem = Path::new(path)
+            .file_name()
+            .and_then(|f| f.to_str())
+            .map_or(path, |f| f.split('.').next().unwrap_or(f));
+        let panel = self.names.len();
+        self.names.push(name.unwrap_or(stem).to_string());
+
+        let rdr = BufReader::new(bgzf::Reader::from_path(path)?);
+        let mut hdr: Option<Vec<String>> = None;
+        let mut panelapp_name = None;
+        // the header, if any, is the first line that isn't a comment or blank
+        let mut first = true;
+        for line in rdr.lines() {
+            let l = line?;
+            if l.trim().is_empty() || l.starts_with('#') {
+                continue;
+            }
+            let cols = l.split('\t').map(|c| c.trim()).collect::<Vec<&str>>();
+            let is_first = std::mem::replace(&mut first, false);
+            // PanelApp downloads have a header with Entity Name and Gene Symbol
+            if is_first && cols.contains(&"Gene Symbol") {
+                hdr = Some(cols.iter().map(|c| c.to_string()).collect());
+                continue;
+            }
+            if is_first && cols[0].eq_ignore_ascii_case("gene") {
+                continue;
+            }
+            let (gene, confidence, moi, alt_ids) = match hdr.as_ref() {
+                Some(h) => {
+                    let get = |name: &str| -> &str {
+                        return h
+                            .iter()
+                            .position(|c| c == name)
+                            .and_then(|i| cols.get(i).copied())
+                            .unwrap_or("");
+                    };
+                    if panelapp_name.is_none() && !get("Level4").is_empty() {
+                        panelapp_name = Some(get("Level4").to_string());
+                    }
+                    (
+                        get("Gene Symbol"),
+                        panelapp_confidence(get("GEL_Status")),
+                        get("Model_Of_Inheritance"),
+                        vec![get("HGNC"), get("EnsemblId(GRch38)"), get("EnsemblId(GRch37)")],
+                    )
+                }
+                None => (
+                    cols[0],
+                    cols.get(1).copied().unwrap_or("").to_string(),
+                    cols.get(2).copied().unwrap_or(""),
+                    vec![],
+                ),
+            };
+            if gene.is_empty() {
+                continue;
+            }
+            self.add(
+                &alt_ids,
+                PanelEntry {
+                    panel,
+                    gene: gene.to_string(),
+                    confidence,
+                    moi: moi.to_string(),
+                },
+            );
+        }
+        if let (None, Some(n)) = (name, panelapp_name) {
+            self.names[panel] = n;
+        }
+        return Ok(());
+    }
+
+    /// a gene already on the panel keeps its first entry
+    fn add(&mut self, alt_ids: &Vec<&str>, entry: PanelEntry) {
+        let row = match self.by_gene.get(&entry.gene) {
+            Some(r) => *r,
+            None => {
+                self.entries.push(vec![]);
+                self.by_gene.insert(entry.gene.clone(), self.entries.len() - 1);
+                self.entries.len() - 1
+            }
+        };
+        for id in alt_ids.iter().filter(|i| !i.is_empty()) {
+            self.by_gene.entry(id.to_string()).or_insert(row);
+        }
+        if !self.entries[row].iter().any(|e| e.panel == entry.panel) {
+            self.entries[row].push(entry);
+        }
+    }
+
+    pub fn add_hdr_fields(&self, hdr: &mut Header, fields: &str) {
+        hdr.push_record(format!("##INFO=<ID=panel,Number=.,Type=String,Description=\"gene panels the genes from {} INFO fields are on, of {}\">", fields, self.names.join("/")).as_bytes());
+        hdr.push_record(r#"##INFO=<ID=panel_gene,Number=.,Type=String,Description="panel gene symbol of each INFO.panel entry">"#.as_bytes());
+        hdr.push_record(r#"##INFO=<ID=panel_confidence,Number=.,Type=String,Description="confidence of each INFO.panel entry, green/amber/red for PanelApp or the second column of a gene list">"#.as_bytes());
+        hdr.push_record(r#"##INFO=<ID=panel_moi,Number=.,Type=String,Description="mode of inheritance of each INFO.panel entry, PanelApp Model_Of_Inheritance or the third column of a gene list">"#.as_bytes());
+        return;
+    }
+
+    /// one value per panel entry of the rows, in row then panel order
+    pub fn add_fields(&self, rows: &Vec<usize>, record: &mut Record) {
+        let entries = rows
+            .iter()
+            .flat_map(|r| self.entries[*r].iter())
+            .collect::<Vec<&PanelEntry>>();
+        let fields: [(&[u8], Vec<String>); 4] = [
+            (b"panel", entries.iter().map(|e| escape_value(&self.names[e.panel])).collect()),
+            (b"panel_gene", entries.iter().map(|e| escape_value(&e.gene)).collect()),
+            (b"panel_confidence", entries.iter().map(|e| escape_value(&e.confidence)).collect()),
+            (b"panel_moi", entries.iter().map(|e| escape_value(&e.moi)).collect()),
+        ];
+        for (id, vals) in fields.iter() {
+            if vals.iter().all(|v| v == ".") {
+                continue;
+            }
+            record
+                .push_info_string(id, &vals.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>())
+                .unwrap_or_else(|_| panic!("failed to set {} field", String::from_utf8_lossy(id)));
+        }
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// a panel file in the temp dir, read then removed
+    fn read(name: &str, text: &str) -> Panels {
+        let fp = std::env::temp_dir().join(format!("bcfutils_{}_{}.tsv", name, std::process::id()));
+        fs::write(&fp, text).unwrap();
+        let panels = Panels::open(&vec![fp.display().to_string()]);
+        fs::remove_file(&fp).unwrap();
+        return panels.unwrap();
+    }
+
+    #[test]
+    fn header_after_comments_and_blank_lines() {
+        let list = read("list", "# my genes\n\ngene\tconfidence\tmoi\nBRCA2\thigh\tAD\n");
+        assert!(!list.by_gene.contains_key("gene"));
+        let e = &list.entries[list.by_gene["BRCA2"]][0];
+        assert_eq!((e.confidence.as_str(), e.moi.as_str()), ("high", "AD"));
+
+        let panelapp = read(
+            "panelapp",
+            "#exported from PanelApp\nEntity Name\tGene Symbol\tGEL_Status\tModel_Of_Inheritance\tLevel4\nBRCA2\tBRCA2\t3\tBIALLELIC\tCancer\n",
+        );
+        assert_eq!(panelapp.names, vec!["Cancer"]);
+        assert!(!panelapp.by_gene.contains_key("Gene Symbol"));
+        let e = &panelapp.entries[panelapp.by_gene["BRCA2"]][0];
+        assert_eq!((e.confidence.as_str(), e.moi.as_str()), ("green", "BIALLELIC"));
+    }
+
+    #[test]
+    fn no_header() {
+        let list = read("bare", "# my genes\nBRCA2\nTP53\n");
+        assert_eq!(list.by_gene.len(), 2);
+    }
+}
@@ -0,0 +1,284 @@
+use bcfutils::pool::StringPool;
+use rust_htslib::bcf::record::{Numeric, Record};
+use rust_htslib::bcf::Header;
+use rust_htslib::bgzf;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnType {
+    Integer,
+    Float,
+    String,
+    Flag,
+}
+
+impl ColumnType {
+    fn parse(t: &str) -> Option<ColumnType> {
+        return match t {
+            "Integer" | "Int" => Some(ColumnType::Integer),
+            "Float" => Some(ColumnType::Float),
+            "String" => Some(ColumnType::String),
+            "Flag" => Some(ColumnType::Flag),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ColumnType::Integer => "Integer",
+            ColumnType::Float => "Float",
+            ColumnType::String => "String",
+            ColumnType::Flag => "Flag",
+        };
+    }
+}
+
+/// a table column written to an INFO field
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub id: String,
+    pub col_type: ColumnType,
+}
+
+/// INFO IDs can't take the brackets and slashes gene table headers like to use
+fn info_id(prefix: &str, name: &str) -> String {
+    return format!("{}{}", prefix, name)
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+}
+
+/// `name:Type,name=ID:Type`, the type defaults to String and the ID to the prefixed name
+pub fn parse_columns(spec: &str, prefix: &str) -> Result<Vec<Column>, String> {
+    let mut columns = vec![];
+    for c in spec.split(',').map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        let (name, col_type) = match c.rsplit_once(':') {
+            Some((name, t)) => match ColumnType::parse(t) {
+                Some(t) => (name, t),
+                None => {
+                    return Err(format!(
+                        "{} in {} is not one of Integer, Float, String or Flag",
+                        t, c
+                    ))
+                }
+            },
+            None => (c, ColumnType::String),
+        };
+        let (name, id) = match name.split_once('=') {
+            Some((name, id)) => (name, info_id(prefix, id)),
+            None => (name, info_id(prefix, name)),
+        };
+        columns.push(Column {
+            name: name.to_string(),
+            id,
+            col_type,
+        });
+    }
+    if columns.is_empty() {
+        return Err(format!("no columns in {}", spec));
+    }
+    return Ok(columns);
+}
+
+/// INFO strings can't hold the separators or whitespace, VCF 4.3 percent encodes them
+pub fn escape_value(val: &str) -> String {
+    if val.is_empty() {
+        return ".".to_string();
+    }
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            ',' => escaped.push_str("%2C"),
+            ';' => escaped.push_str("%3B"),
+            '=' => escaped.push_str("%3D"),
+            ' ' => escaped.push_str("%20"),
+            '\t' => escaped.push_str("%09"),
+            '\n' => escaped.push_str("%0A"),
+            '\r' => escaped.push_str("%0D"),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// a gene level table keyed on one column, the requested columns of each row are kept
+/// columnar as StringPool ids
+pub struct GeneTable {
+    pub path: String,
+    pub columns: Vec<Column>,
+    pub by_gene: HashMap<String, usize>,
+    values: Vec<u32>,
+    pool: StringPool,
+}
+
+impl GeneTable {
+    /// tab separated with a header line, plain or gzipped
+    pub fn open(path: &str, key_column: &str, columns: Vec<Column>) -> Result<GeneTable, Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .quoting(false)
+            .flexible(true)
+            .from_reader(bgzf::Reader::from_path(path)?);
+        let hdr = rdr
+            .headers()?
+            .iter()
+            .map(|h| h.trim_start_matches('#').to_string())
+            .collect::<Vec<String>>();
+        let find = |name: &str| -> Result<usize, String> {
+            return hdr
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| format!("{} has no {} column", path, name));
+        };
+        let key = find(key_column)?;
+        let idxs = columns
+            .iter()
+            .map(|c| find(&c.name))
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        let mut by_gene = HashMap::new();
+        let mut values = vec![];
+        let mut pool = StringPool::default();
+        for result in rdr.records() {
+            let row = result?;
+            let gene = match row.get(key) {
+                Some(g) if !g.is_empty() => g.to_string(),
+                _ => continue,
+            };
+            by_gene.insert(gene, values.len() / idxs.len());
+            for i in &idxs {
+                values.push(pool.intern(row.get(*i).unwrap_or("")));
+            }
+        }
+        pool.freeze();
+        values.shrink_to_fit();
+        return Ok(GeneTable {
+            path: path.to_string(),
+            columns,
+            by_gene,
+            values,
+            pool,
+        });
+    }
+
+    /// the requested column values of a by_gene row
+    pub fn row(&self, row: usize) -> Vec<&str> {
+        let n = self.columns.len();
+        return self.values[row * n..(row + 1) * n]
+            .iter()
+            .map(|id| self.pool.get(*id))
+            .collect();
+    }
+
+    /// with all_genes a value per gene, Flags are set when any of the genes has them
+    pub fn add_hdr_fields(&self, hdr: &mut Header, fields: &str, all_genes: bool) {
+        let file = Path::new(&self.path)
+            .file_name()
+            .map_or(self.path.as_str(), |f| f.to_str().unwrap_or(&self.path));
+        for c in &self.columns {
+            let number = match (c.col_type, all_genes) {
+                (ColumnType::Flag, _) => "0",
+                (_, true) => ".",
+                (_, false) => "1",
+            };
+            hdr.push_record(format!("##INFO=<ID={},Number={},Type={},Description=\"{} from {}, using {} INFO fields to lookup\">", c.id, number, c.col_type.as_str(), c.name, file, fields).as_bytes());
+        }
+        return;
+    }
+
+    /// missing and unparseable values are left off, like the invalid_option gnomAD columns
+    pub fn add_fields(&self, row: usize, record: &mut Record) {
+        for (c, v) in self.columns.iter().zip(self.row(row)) {
+            if v.is_empty() || v == "." || v == "NA" {
+                continue;
+            }
+            let id = c.id.as_bytes();
+            let res = match c.col_type {
+                ColumnType::Integer => match v.parse::<i32>() {
+                    Ok(i) => record.push_info_integer(id, &[i]),
+                    Err(_) => continue,
+                },
+                ColumnType::Float => match v.parse::<f32>() {
+                    Ok(f) => record.push_info_float(id, &[f]),
+                    Err(_) => continue,
+                },
+                ColumnType::String => record.push_info_string(id, &[escape_value(v).as_bytes()]),
+                ColumnType::Flag => match v.to_ascii_lowercase().as_str() {
+                    "0" | "false" | "no" | "n" => continue,
+                    _ => record.push_info_flag(id),
+                },
+            };
+            res.unwrap_or_else(|_| panic!("failed to set {} field", c.id));
+        }
+        return;
+    }
+
+    /// --all-genes, one value per row in order with missing and unparseable ones written as .
+    pub fn add_multi_fields(&self, rows: &Vec<usize>, record: &mut Record) {
+        let rows = rows.iter().map(|r| self.row(*r)).collect::<Vec<Vec<&str>>>();
+        for (i, c) in self.columns.iter().enumerate() {
+            let vals = rows
+                .iter()
+                .map(|r| r[i])
+                .map(|v| match v {
+                    "" | "." | "NA" => None,
+                    v => Some(v),
+                })
+                .collect::<Vec<Option<&str>>>();
+            let id = c.id.as_bytes();
+            let res = match c.col_type {
+                ColumnType::Integer => {
+                    let vals = vals
+                        .iter()
+                        .map(|v| v.and_then(|v| v.parse::<i32>().ok()))
+                        .collect::<Vec<Option<i32>>>();
+                    if vals.iter().all(|v| v.is_none()) {
+                        continue;
+                    }
+                    record.push_info_integer(
+                        id,
+                        &vals.iter().map(|v| v.unwrap_or_else(i32::missing)).collect::<Vec<i32>>(),
+                    )
+                }
+                ColumnType::Float => {
+                    let vals = vals
+                        .iter()
+                        .map(|v| v.and_then(|v| v.parse::<f32>().ok()))
+                        .collect::<Vec<Option<f32>>>();
+                    if vals.iter().all(|v| v.is_none()) {
+                        continue;
+                    }
+                    record.push_info_float(
+                        id,
+                        &vals.iter().map(|v| v.unwrap_or_else(f32::missing)).collect::<Vec<f32>>(),
+                    )
+                }
+                ColumnType::String => {
+                    if vals.iter().all(|v| v.is_none()) {
+                        continue;
+                    }
+                    let vals = vals
+                        .iter()
+                        .map(|v| v.map_or(".".to_string(), escape_value))
+                        .collect::<Vec<String>>();
+                    record.push_info_string(id, &vals.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>())
+                }
+                ColumnType::Flag => match vals.iter().flatten().any(|v| {
+                    !matches!(v.to_ascii_lowercase().as_str(), "0" | "false" | "no" | "n")
+                }) {
+                    true => record.push_info_flag(id),
+                    false => continue,
+                },
+            };
+            res.unwrap_or_else(|_| panic!("failed to set {} field", c.id));
+        }
+        return;
+    }
+}
@@ -0,0 +1,567 @@
+use super::reference::{revcomp, Reference};
+use bcfutils::gff::TrxStructure;
+use std::collections::HashMap;
+
+/// how far to look for 3' shifting of indels
+const SHIFT_WINDOW: u64 = 100;
+/// transcripts CdsCache holds before it starts over
+const CDS_CACHE_SIZE: usize = 64;
+
+const BASES: [u8; 4] = [b'T', b'C', b'A', b'G'];
+// standard code, codons ordered TTT, TTC, TTA, TTG, TCT, ...
+const AMINO_ACIDS: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+fn aa_name(aa: u8) -> &'static str {
+    return match aa {
+        b'A' => "Ala",
+        b'R' => "Arg",
+        b'N' => "Asn",
+        b'D' => "Asp",
+        b'C' => "Cys",
+        b'Q' => "Gln",
+        b'E' => "Glu",
+        b'G' => "Gly",
+        b'H' => "His",
+        b'I' => "Ile",
+        b'L' => "Leu",
+        b'K' => "Lys",
+        b'M' => "Met",
+        b'F' => "Phe",
+        b'P' => "Pro",
+        b'S' => "Ser",
+        b'T' => "Thr",
+        b'W' => "Trp",
+        b'Y' => "Tyr",
+        b'V' => "Val",
+        b'*' => "Ter",
+        _ => "Xaa",
+    };
+}
+
+fn aa_names(aas: &[u8]) -> String {
+    return aas.iter().map(|a| aa_name(*a)).collect();
+}
+
+/// translate up to and including the first stop
+fn translate(seq: &[u8]) -> Vec<u8> {
+    let mut prot = vec![];
+    for codon in seq.chunks_exact(3) {
+        let idx = codon.iter().try_fold(0, |idx, b| {
+            BASES.iter().position(|x| x == b).map(|i| idx * 4 + i)
+        });
+        let aa = idx.map_or(b'X', |i| AMINO_ACIDS[i]);
+        prot.push(aa);
+        if aa == b'*' {
+            break;
+        }
+    }
+    return prot;
+}
+
+/// a variant trimmed to its minimal form. deletions and substitutions cover [pos, pos+ref-1],
+/// insertions go between pos-1 and pos
+struct Edit {
+    pos: u64,
+    rf: String,
+    alt: String,
+}
+
+fn trim(pos: u64, rf: &str, alt: &str) -> Edit {
+    let (mut rf, mut alt) = (rf.as_bytes(), alt.as_bytes());
+    while !rf.is_empty() && !alt.is_empty() && rf[rf.len() - 1] == alt[alt.len() - 1] {
+        rf = &rf[..rf.len() - 1];
+        alt = &alt[..alt.len() - 1];
+    }
+    let mut pos = pos;
+    while !rf.is_empty() && !alt.is_empty() && rf[0] == alt[0] {
+        rf = &rf[1..];
+        alt = &alt[1..];
+        pos += 1;
+    }
+    return Edit {
+        pos,
+        rf: String::from_utf8_lossy(rf).to_string(),
+        alt: String::from_utf8_lossy(alt).to_string(),
+    };
+}
+
+/// the exon, intron or flank of the transcript holding the bases a to b, None when they
+/// straddle a boundary
+fn shift_bounds(st: &TrxStructure, a: u64, b: u64) -> Option<(u64, u64)> {
+    let (first, last) = (st.exons.first()?.0, st.exons.last()?.1);
+    if b < first {
+        return Some((1, first - 1));
+    } else if a > last {
+        return Some((last + 1, u64::MAX));
+    }
+    return st
+        .exons
+        .iter()
+        .copied()
+        .chain(st.introns())
+        .find(|(s, e)| a >= *s && b <= *e);
+}
+
+/// move a deletion or insertion as far 3' as it goes in transcript orientation, without
+/// leaving the exon or intron it is in
+fn shift_3p(e: &mut Edit, st: &TrxStructure, chrom: &str, fasta: &Reference) {
+    let plus = st.strand != '-';
+    // the deleted bases, or the base 5' of an insertion, it can end up after the last one
+    let (a, b) = match (e.rf.is_empty(), plus) {
+        (true, true) => (e.pos - 1, e.pos - 1),
+        (true, false) => (e.pos, e.pos),
+        (false, _) => (e.pos, e.pos + e.rf.len() as u64 - 1),
+    };
+    let (lo, hi) = match shift_bounds(st, a, b) {
+        Some(b) => b,
+        None => return,
+    };
+    let (flo, fhi) = (e.pos.saturating_sub(SHIFT_WINDOW).max(1), e.pos + SHIFT_WINDOW);
+    let flank = match fasta.fetch(chrom, flo, fhi) {
+        Some(f) => f.into_bytes(),
+        None => return,
+    };
+    let base = |p: u64| -> Option<u8> {
+        return match p >= lo.max(flo) && p <= hi {
+            true => flank.get((p - flo) as usize).copied(),
+            false => None,
+        };
+    };
+    if !e.rf.is_empty() && e.alt.is_empty() {
+        let len = e.rf.len() as u64;
+        loop {
+            let mut s = e.rf.clone().into_bytes();
+            match plus {
+                true if base(e.pos + len) == Some(s[0]) => {
+                    s.rotate_left(1);
+                    e.pos += 1;
+                }
+                false if e.pos > 1 && base(e.pos - 1) == s.last().copied() => {
+                    s.rotate_right(1);
+                    e.pos -= 1;
+                }
+                _ => break,
+            }
+            e.rf = String::from_utf8_lossy(&s).to_string();
+        }
+    } else if e.rf.is_empty() && !e.alt.is_empty() {
+        loop {
+            let mut s = e.alt.clone().into_bytes();
+            match plus {
+                true if base(e.pos) == Some(s[0]) => {
+                    s.rotate_left(1);
+                    e.pos += 1;
+                }
+                false if e.pos > 1 && base(e.pos - 1) == s.last().copied() => {
+                    s.rotate_right(1);
+                    e.pos -= 1;
+                }
+                _ => break,
+            }
+            e.alt = String::from_utf8_lossy(&s).to_string();
+        }
+    }
+}
+
+/// c. coordinate of a genomic position, with intronic offsets and UTR prefixes. n. style
+/// transcript coordinates for non-coding transcripts. None outside the transcript
+fn coord(st: &TrxStructure, pos: u64) -> Option<String> {
+    let tx_coord = |tx: u64| -> String {
+        let (cs, ce) = match cds_bounds(st) {
+            Some(b) => b,
+            None => return tx.to_string(),
+        };
+        if tx < cs {
+            return format!("-{}", cs - tx);
+        } else if tx > ce {
+            return format!("*{}", tx - ce);
+        }
+        return (tx - cs + 1).to_string();
+    };
+    if let Some((tx, _)) = st.exon_pos(pos) {
+        return Some(tx_coord(tx));
+    }
+    // intronic, count from the closer exon end, the 5' one on a tie
+    for (is, ie) in st.introns() {
+        if pos < is || pos > ie {
+            continue;
+        }
+        let (up, down) = match st.strand {
+            '-' => ((ie + 1, ie - pos + 1), (is - 1, pos - is + 1)),
+            _ => ((is - 1, pos - is + 1), (ie + 1, ie - pos + 1)),
+        };
+        return match up.1 <= down.1 {
+            true => Some(format!("{}+{}", tx_coord(st.exon_pos(up.0)?.0), up.1)),
+            false => Some(format!("{}-{}", tx_coord(st.exon_pos(down.0)?.0), down.1)),
+        };
+    }
+    return None;
+}
+
+/// transcript coordinates of the first and last CDS base
+fn cds_bounds(st: &TrxStructure) -> Option<(u64, u64)> {
+    let (first, last) = match st.strand {
+        '-' => (st.cds.last()?.1, st.cds.first()?.0),
+        _ => (st.cds.first()?.0, st.cds.last()?.1),
+    };
+    return Some((st.exon_pos(first)?.0, st.exon_pos(last)?.0));
+}
+
+fn coord_range(st: &TrxStructure, start: u64, end: u64) -> Option<String> {
+    let (a, b) = match st.strand {
+        '-' => (end, start),
+        _ => (start, end),
+    };
+    if a == b {
+        return coord(st, a);
+    }
+    return Some(format!("{}_{}", coord(st, a)?, coord(st, b)?));
+}
+
+fn hgvs_c(st: &TrxStructure, e: &Edit, chrom: &str, fasta: &Reference) -> Option<String> {
+    let orient = |s: &str| -> String {
+        return match st.strand {
+            '-' => revcomp(s),
+            _ => s.to_string(),
+        };
+    };
+    let prefix = match st.cds.is_empty() {
+        true => "n.",
+        false => "c.",
+    };
+    let (rf, alt) = (orient(&e.rf), orient(&e.alt));
+    let end = e.pos + e.rf.len() as u64 - 1;
+    let change = match (e.rf.len(), e.alt.len()) {
+        (1, 1) => format!("{}{}>{}", coord(st, e.pos)?, rf, alt),
+        (_, 0) => format!("{}del", coord_range(st, e.pos, end)?),
+        (0, n) => {
+            let n = n as u64;
+            // the copy the insertion would duplicate sits 5' of it in transcript orientation
+            let (ds, de) = match st.strand {
+                '-' => (e.pos, e.pos + n - 1),
+                _ => (e.pos.saturating_sub(n), e.pos - 1),
+            };
+            match fasta.fetch(chrom, ds, de) {
+                Some(d) if d == e.alt => format!("{}dup", coord_range(st, ds, de)?),
+                _ => format!("{}ins{}", coord_range(st, e.pos - 1, e.pos)?, alt),
+            }
+        }
+        (_, _) => format!("{}delins{}", coord_range(st, e.pos, end)?, alt),
+    };
+    return Some(format!("{}{}", prefix, change));
+}
+
+/// CDS sequence in transcript orientation
+fn cds_seq(st: &TrxStructure, chrom: &str, fasta: &Reference) -> Option<Vec<u8>> {
+    let mut seq = String::new();
+    for (s, end) in st.cds.iter() {
+        seq.push_str(&fasta.fetch(chrom, *s, *end)?);
+    }
+    return match st.strand {
+        '-' => Some(revcomp(&seq).into_bytes()),
+        _ => Some(seq.into_bytes()),
+    };
+}
+
+/// reference CDS and protein of the transcripts seen last. records come sorted, so the same
+/// few transcripts are asked for over and over
+#[derive(Default)]
+pub struct CdsCache {
+    by_transcript: HashMap<String, (Vec<u8>, Vec<u8>)>,
+}
+
+impl CdsCache {
+    fn get(&mut self, transcript: &str, st: &TrxStructure, chrom: &str, fasta: &Reference) -> Option<&(Vec<u8>, Vec<u8>)> {
+        if !self.by_transcript.contains_key(transcript) {
+            if self.by_transcript.len() >= CDS_CACHE_SIZE {
+                self.by_transcript.clear();
+            }
+            let cds = cds_seq(st, chrom, fasta)?;
+            let prot = translate(&cds);
+            self.by_transcript.insert(transcript.to_string(), (cds, prot));
+        }
+        return self.by_transcript.get(transcript);
+    }
+}
+
+/// the protein with rf_len bases at `at` of the CDS replaced by alt, both in transcript
+/// orientation. only translated from the codon the edit starts in, and for in frame edits
+/// only up to the last codon it touches
+fn alt_protein(cds: &[u8], rp: &[u8], at: usize, rf_len: usize, alt: &[u8]) -> Vec<u8> {
+    let codon = at / 3;
+    // past the reference stop
+    if codon >= rp.len() {
+        return rp.to_vec();
+    }
+    let mut ap = rp[..codon].to_vec();
+    let mut seq = cds[codon * 3..at].to_vec();
+    seq.extend_from_slice(alt);
+    if (alt.len() as i64 - rf_len as i64) % 3 != 0 {
+        seq.extend_from_slice(&cds[at + rf_len..]);
+        ap.extend(translate(&seq));
+        return ap;
+    }
+    let ends = (at + rf_len).div_ceil(3).max(codon);
+    seq.extend_from_slice(&cds[at + rf_len..(ends * 3).min(cds.len())]);
+    let window = translate(&seq);
+    let stopped = window.last() == Some(&b'*');
+    ap.extend(window);
+    if !stopped && ends < rp.len() {
+        ap.extend_from_slice(&rp[ends..]);
+    }
+    return ap;
+}
+
+fn hgvs_p(
+    transcript: &str,
+    st: &TrxStructure,
+    e: &Edit,
+    chrom: &str,
+    fasta: &Reference,
+    cache: &mut CdsCache,
+) -> Option<String> {
+    let end = e.pos + (e.rf.len() as u64).max(1) - 1;
+    let start = match e.rf.is_empty() {
+        true => e.pos - 1,
+        false => e.pos,
+    };
+    let in_cds = |p: u64| st.cds.iter().position(|(s, e)| p >= *s && p <= *e);
+    let touches_cds = st.cds.iter().any(|(s, ce)| start <= *ce && end >= *s);
+    if !touches_cds {
+        return None;
+    }
+    // anything reaching past a CDS segment end can change splicing
+    let seg = match (in_cds(start), in_cds(end)) {
+        (Some(a), Some(b)) if a == b => a,
+        _ => return Some("p.?".to_string()),
+    };
+
+    let (cds, rp) = cache.get(transcript, st, chrom, fasta)?;
+    // where the edit is in the CDS, insertions go before this base
+    let at = (st.cds[..seg].iter().map(|(s, e)| e - s + 1).sum::<u64>() + e.pos - st.cds[seg].0) as usize;
+    let ap = match st.strand {
+        '-' => alt_protein(cds, rp, cds.len() - at - e.rf.len(), e.rf.len(), revcomp(&e.alt).as_bytes()),
+        _ => alt_protein(cds, rp, at, e.rf.len(), e.alt.as_bytes()),
+    };
+    let fs = (e.alt.len() as i64 - e.rf.len() as i64) % 3 != 0;
+
+    let first = match rp.iter().zip(ap.iter()).position(|(r, a)| r != a) {
+        Some(i) => i,
+        None if rp.len() == ap.len() => {
+            let (c, _) = st.cds_pos(e.pos)?;
+            let i = ((c - 1) / 3) as usize;
+            return Some(format!("p.({}{}=)", aa_name(*rp.get(i)?), i + 1));
+        }
+        None => rp.len().min(ap.len()),
+    };
+    if first == 0 {
+        return Some("p.(Met1?)".to_string());
+    }
+    let r_aa = match rp.get(first) {
+        Some(a) => *a,
+        None => return Some("p.?".to_string()),
+    };
+    if r_aa == b'*' {
+        let a_aa = ap.get(first).copied().unwrap_or(b'X');
+        return Some(format!("p.(Ter{}{}extTer?)", first + 1, aa_name(a_aa)));
+    }
+    let a_aa = match ap.get(first) {
+        Some(a) => *a,
+        None => return Some("p.?".to_string()),
+    };
+    if a_aa == b'*' {
+        return Some(format!("p.({}{}Ter)", aa_name(r_aa), first + 1));
+    }
+    if fs {
+        let ter = match ap.last() {
+            Some(b'*') => (ap.len() - first).to_string(),
+            _ => "?".to_string(),
+        };
+        return Some(format!("p.({}{}{}fsTer{})", aa_name(r_aa), first + 1, aa_name(a_aa), ter));
+    }
+
+    // in frame, the changed stretch is what's left after the shared prefix and suffix
+    let mut suffix = 0;
+    while suffix < rp.len() - first
+        && suffix < ap.len() - first
+        && rp[rp.len() - 1 - suffix] == ap[ap.len() - 1 - suffix]
+    {
+        suffix += 1;
+    }
+    let (del, ins) = (&rp[first..rp.len() - suffix], &ap[first..ap.len() - suffix]);
+    let span = |s: usize, e: usize| -> String {
+        return match s == e {
+            true => format!("{}{}", aa_name(rp[s]), s + 1),
+            false => format!("{}{}_{}{}", aa_name(rp[s]), s + 1, aa_name(rp[e]), e + 1),
+        };
+    };
+    let change = match (del.len(), ins.len()) {
+        (1, 1) => format!("{}{}{}", aa_name(del[0]), first + 1, aa_name(ins[0])),
+        (_, 0) => format!("{}del", span(first, first + del.len() - 1)),
+        (0, n) if first >= n && &rp[first - n..first] == ins => {
+            format!("{}dup", span(first - n, first - 1))
+        }
+        (0, _) => format!("{}ins{}", span(first - 1, first), aa_names(ins)),
+        (_, _) => format!("{}delins{}", span(first, first + del.len() - 1), aa_names(ins)),
+    };
+    return Some(format!("p.({})", change));
+}
+
+/// HGVS c. (or n.) and p. descriptions of an ALT allele on a transcript. the c. one is
+/// prefixed with the transcript id, p. is None for changes outside the CDS
+pub fn hgvs(
+    transcript: &str,
+    structure: &str,
+    chrom: &str,
+    pos: u64,
+    rf: &str,
+    alt: &str,
+    fasta: &Reference,
+    cache: &mut CdsCache,
+) -> (Option<String>, Option<String>) {
+    let st = match TrxStructure::decode(structure) {
+        Some(st) if !st.exons.is_empty() => st,
+        _ => return (None, None),
+    };
+    if alt.is_empty() || alt.contains(['<', '*', '[', ']', '.']) {
+        return (None, None);
+    }
+    let mut e = trim(pos, rf, alt);
+    if e.rf.is_empty() && e.alt.is_empty() {
+        return (None, None);
+    }
+    if e.rf.is_empty() || e.alt.is_empty() {
+        shift_3p(&mut e, &st, chrom, fasta);
+    }
+    let c = hgvs_c(&st, &e, chrom, fasta).map(|c| format!("{}:{}", transcript, c));
+    let p = hgvs_p(transcript, &st, &e, chrom, fasta, cache);
+    return (c, p);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 5'UTR 11-20, CDS 21-40 and 61-79, intron 41-60, 3'UTR 80-100. the CDS reads
+    // ATG GCT GAA AAA CTG ATT GGC CCA AGT CAG TGG GAC TAA, c.8-12 is a run of A's and
+    // reading it one base on from c.14 hits TGA
+    const PLUS_SEQ: &str = concat!(
+        "TTTTTTTTTT",
+        "CCCCCGCCCC",
+        "ATGGCTGAAAAACTGATTGG",
+        "GTCCCCCCCCCCCCCCCCAG",
+        "CCCAAGTCAGTGGGACTAA",
+        "CCCCCCCCCCCCCCCCCCCCC",
+    );
+    const PLUS: &str = "+;11-40,61-100;21-40,61-79";
+    // the same transcript on the minus strand, genomic p is 101-p of the plus one
+    const MINUS: &str = "-;1-40,61-90;22-40,61-80";
+
+    fn references() -> (Reference, Reference) {
+        let pad = "C".repeat(200);
+        let plus = Reference::from_seq("hgvs_plus", "chr1", &format!("{}{}", PLUS_SEQ, pad));
+        let minus = Reference::from_seq("hgvs_minus", "chr1", &format!("{}{}", revcomp(PLUS_SEQ), pad));
+        return (plus, minus);
+    }
+
+    // structure, pos, ref, alt, expected c. and p.
+    type Case<'a> = (&'a str, u64, &'a str, &'a str, Option<&'a str>, Option<&'a str>);
+
+    fn check(cases: &[Case], fasta: &Reference) {
+        let mut cache = CdsCache::default();
+        for (st, pos, rf, alt, c, p) in cases {
+            let (hc, hp) = hgvs("T1", st, "chr1", *pos, rf, alt, fasta, &mut cache);
+            assert_eq!(hc.as_deref(), c.map(|c| format!("T1:{}", c)).as_deref(), "{} {}>{} on {}", pos, rf, alt, st);
+            assert_eq!(hp.as_deref(), *p, "{} {}>{} on {}", pos, rf, alt, st);
+        }
+    }
+
+    #[test]
+    fn substitutions() {
+        let (plus, minus) = references();
+        check(
+            &[
+                (PLUS, 24, "G", "A", Some("c.4G>A"), Some("p.(Ala2Thr)")),
+                (PLUS, 26, "T", "C", Some("c.6T>C"), Some("p.(Ala2=)")),
+                (PLUS, 30, "A", "T", Some("c.10A>T"), Some("p.(Lys4Ter)")),
+                // c.21 is the first base of the second exon
+                (PLUS, 61, "C", "T", Some("c.21C>T"), Some("p.(Gly7=)")),
+                (PLUS, 77, "T", "C", Some("c.37T>C"), Some("p.(Ter13GlnextTer?)")),
+            ],
+            &plus,
+        );
+        check(
+            &[
+                (MINUS, 77, "C", "T", Some("c.4G>A"), Some("p.(Ala2Thr)")),
+                (MINUS, 75, "A", "G", Some("c.6T>C"), Some("p.(Ala2=)")),
+                (MINUS, 71, "T", "A", Some("c.10A>T"), Some("p.(Lys4Ter)")),
+                (MINUS, 40, "G", "A", Some("c.21C>T"), Some("p.(Gly7=)")),
+            ],
+            &minus,
+        );
+    }
+
+    #[test]
+    fn intronic_and_utr_positions() {
+        let (plus, minus) = references();
+        check(
+            &[
+                (PLUS, 42, "T", "C", Some("c.20+2T>C"), None),
+                (PLUS, 59, "A", "G", Some("c.21-2A>G"), None),
+                // the middle of a 20bp intron counts from the 5' exon
+                (PLUS, 50, "C", "T", Some("c.20+10C>T"), None),
+                (PLUS, 51, "C", "T", Some("c.21-10C>T"), None),
+                (PLUS, 15, "C", "T", Some("c.-6C>T"), None),
+                (PLUS, 20, "C", "T", Some("c.-1C>T"), None),
+                (PLUS, 80, "C", "T", Some("c.*1C>T"), None),
+                (PLUS, 85, "C", "T", Some("c.*6C>T"), None),
+                // outside the transcript
+                (PLUS, 5, "T", "C", None, None),
+            ],
+            &plus,
+        );
+        check(
+            &[
+                (MINUS, 59, "A", "G", Some("c.20+2T>C"), None),
+                (MINUS, 42, "T", "C", Some("c.21-2A>G"), None),
+                (MINUS, 51, "G", "A", Some("c.20+10C>T"), None),
+                (MINUS, 50, "G", "A", Some("c.21-10C>T"), None),
+                (MINUS, 86, "G", "A", Some("c.-6C>T"), None),
+                (MINUS, 16, "G", "A", Some("c.*6C>T"), None),
+            ],
+            &minus,
+        );
+    }
+
+    #[test]
+    fn indels_shift_3p() {
+        let (plus, minus) = references();
+        check(
+            &[
+                // an A deleted or inserted anywhere in c.8-12 is written at c.12
+                (PLUS, 27, "GA", "G", Some("c.12del"), Some("p.(Lys4AsnfsTer2)")),
+                (PLUS, 30, "AA", "A", Some("c.12del"), Some("p.(Lys4AsnfsTer2)")),
+                // no stop left in the CDS
+                (PLUS, 28, "A", "AA", Some("c.12dup"), Some("p.(Leu5ThrfsTer?)")),
+                (PLUS, 32, "A", "AG", Some("c.12_13insG"), Some("p.(Leu5AlafsTer?)")),
+                (PLUS, 27, "GAAA", "G", Some("c.10_12del"), Some("p.(Lys4del)")),
+                (PLUS, 32, "A", "AAAA", Some("c.10_12dup"), Some("p.(Lys4dup)")),
+                // c.19-20 and c.20+1 are all G, shifting stops at the end of the exon
+                (PLUS, 38, "TG", "T", Some("c.20del"), Some("p.(Gly7AlafsTer?)")),
+                (PLUS, 38, "T", "TG", Some("c.20dup"), Some("p.?")),
+                (PLUS, 40, "G", "GG", Some("c.20dup"), Some("p.?")),
+            ],
+            &plus,
+        );
+        check(
+            &[
+                (MINUS, 70, "TT", "T", Some("c.12del"), Some("p.(Lys4AsnfsTer2)")),
+                (MINUS, 70, "T", "TT", Some("c.12dup"), Some("p.(Leu5ThrfsTer?)")),
+                (MINUS, 68, "G", "GC", Some("c.12_13insG"), Some("p.(Leu5AlafsTer?)")),
+                (MINUS, 68, "GTTT", "G", Some("c.10_12del"), Some("p.(Lys4del)")),
+                (MINUS, 61, "CC", "C", Some("c.20del"), Some("p.(Gly7AlafsTer?)")),
+            ],
+            &minus,
+        );
+    }
+}
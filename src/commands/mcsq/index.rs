@@ -0,0 +1,142 @@
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, BufWriter};
+
+// layout, all integers little endian:
+// magic [8] | version u32 | n u32 | gff checksum u64
+// n * (key_off u32, key_len u32, val_off u32, val_len u32, st_off u32, st_len u32), sorted by key
+// string blob, offsets are relative to its start
+const MAGIC: &[u8; 8] = b"MCSQTRX\0";
+/// bump whenever the layout or the appended subfields change
+pub const INDEX_VERSION: u32 = 4;
+const HDR_LEN: usize = 24;
+const ENTRY_LEN: usize = 24;
+
+/// FNV-1a over the raw bytes of the gff, used to tell if an index is stale
+pub fn checksum_file(fp: &str) -> Result<u64, Box<dyn Error>> {
+    let mut rdr = BufReader::new(File::open(fp)?);
+    let mut hash: u64 = 0xcbf29ce484222325;
+    loop {
+        let buf = rdr.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        for b in buf {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        let len = buf.len();
+        rdr.consume(len);
+    }
+    return Ok(hash);
+}
+
+pub fn write_index(
+    map: &HashMap<String, (String, String)>,
+    checksum: u64,
+    fp: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut keys = map.keys().collect::<Vec<&String>>();
+    keys.sort();
+
+    let mut entries = Vec::with_capacity(keys.len() * ENTRY_LEN);
+    let mut blob = vec![];
+    for k in keys {
+        let (v, st) = &map[k];
+        for s in [k, v, st] {
+            entries.extend_from_slice(&(blob.len() as u32).to_le_bytes());
+            entries.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            blob.extend_from_slice(s.as_bytes());
+        }
+    }
+
+    let mut w = BufWriter::new(File::create(fp)?);
+    w.write_all(MAGIC)?;
+    w.write_all(&INDEX_VERSION.to_le_bytes())?;
+    w.write_all(&(map.len() as u32).to_le_bytes())?;
+    w.write_all(&checksum.to_le_bytes())?;
+    w.write_all(&entries)?;
+    w.write_all(&blob)?;
+    w.flush()?;
+    return Ok(());
+}
+
+/// memory mapped transcript table written by `mcsq build-index`
+pub struct TrxIndex {
+    mmap: Mmap,
+    n: usize,
+    pub checksum: u64,
+}
+
+fn read_u32(b: &[u8], at: usize) -> usize {
+    return u32::from_le_bytes(b[at..at + 4].try_into().unwrap()) as usize;
+}
+
+impl TrxIndex {
+    pub fn open(fp: &str) -> Result<TrxIndex, Box<dyn Error>> {
+        let f = File::open(fp)?;
+        let mmap = unsafe { Mmap::map(&f)? };
+        if mmap.len() < HDR_LEN || &mmap[0..8] != MAGIC {
+            return Err(format!("{} is not an mcsq transcript index", fp).into());
+        }
+        let version = read_u32(&mmap, 8) as u32;
+        if version != INDEX_VERSION {
+            return Err(format!(
+                "{} is index version {}, this mcsq reads version {}, rebuild it with `mcsq build-index`",
+                fp, version, INDEX_VERSION
+            )
+            .into());
+        }
+        let n = read_u32(&mmap, 12);
+        let checksum = u64::from_le_bytes(mmap[16..24].try_into().unwrap());
+        let idx = TrxIndex { mmap, n, checksum };
+        let blob_len = match idx.mmap.len().checked_sub(idx.blob_start()) {
+            Some(bl) => bl,
+            None => return Err(format!("{} is truncated or corrupt", fp).into()),
+        };
+        for i in 0..n {
+            if idx.entry(i).iter().any(|(o, l)| o + l > blob_len) {
+                return Err(format!("{} is truncated or corrupt", fp).into());
+            }
+        }
+        return Ok(idx);
+    }
+
+    fn blob_start(&self) -> usize {
+        return HDR_LEN + self.n * ENTRY_LEN;
+    }
+
+    /// (offset, length) of the key, appended subfields, and structure of entry i
+    fn entry(&self, i: usize) -> [(usize, usize); 3] {
+        let at = HDR_LEN + i * ENTRY_LEN;
+        return [0, 8, 16].map(|o| {
+            (
+                read_u32(&self.mmap, at + o),
+                read_u32(&self.mmap, at + o + 4),
+            )
+        });
+    }
+
+    fn str_at(&self, off: usize, len: usize) -> &str {
+        let start = self.blob_start() + off;
+        return std::str::from_utf8(&self.mmap[start..start + len]).unwrap_or("");
+    }
+
+    pub fn get(&self, key: &str) -> Option<(&str, &str)> {
+        let (mut lo, mut hi) = (0, self.n);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let [(ko, kl), (vo, vl), (so, sl)] = self.entry(mid);
+            match self.str_at(ko, kl).cmp(key) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    return Some((self.str_at(vo, vl), self.str_at(so, sl)))
+                }
+            }
+        }
+        return None;
+    }
+}
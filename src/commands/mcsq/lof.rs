@@ -0,0 +1,189 @@
+use super::consequence_terms;
+use super::reference::{revcomp, Reference};
+use bcfutils::gff::TrxStructure;
+
+// LOFTEE style filters, any one of them makes a predicted LoF low confidence
+const END_TRUNC: &str = "END_TRUNC";
+const INCOMPLETE_CDS: &str = "INCOMPLETE_CDS";
+const SINGLE_EXON: &str = "SINGLE_EXON";
+const NON_CAN_SPLICE: &str = "NON_CAN_SPLICE";
+
+/// fraction of the CDS after which a truncating variant is treated as escaping
+const END_TRUNC_FRAC: f64 = 0.95;
+
+fn lof_terms(consequence: &str) -> impl Iterator<Item = &str> {
+    return consequence_terms(consequence).filter(|c| matches!(*c, "stop_gained" | "frameshift" | "splice_donor" | "splice_acceptor"));
+}
+
+/// true when an intron end touched by [start, end] isn't GT/GC..AG on the reference
+fn non_canonical_splice(
+    st: &TrxStructure,
+    chrom: &str,
+    start: u64,
+    end: u64,
+    fasta: &Reference,
+) -> bool {
+    for (is, ie) in st.introns() {
+        // 2bp at each intron end, with whether that end is the donor in transcript orientation
+        for (ws, we, is_donor) in [(is, is + 1, st.strand != '-'), (ie - 1, ie, st.strand == '-')] {
+            if we < start || ws > end {
+                continue;
+            }
+            let seq = match fasta.fetch(chrom, ws, we) {
+                Some(s) => s,
+                None => continue,
+            };
+            let seq = match st.strand {
+                '-' => revcomp(&seq),
+                _ => seq,
+            };
+            let canonical = match is_donor {
+                true => seq == "GT" || seq == "GC",
+                false => seq == "AG",
+            };
+            if !canonical {
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+/// HC or LC and the filters that fired for a predicted LoF consequence, None when the
+/// consequence isn't LoF. structure is the encoded exon/CDS structure, empty when unknown
+pub fn lof_confidence(
+    consequence: &str,
+    unknown_start_end: &str,
+    structure: &str,
+    chrom: &str,
+    pos: u64,
+    ref_len: u64,
+    fasta: Option<&Reference>,
+) -> Option<(&'static str, Vec<&'static str>)> {
+    let terms = lof_terms(consequence).collect::<Vec<&str>>();
+    if terms.is_empty() {
+        return None;
+    }
+    let mut filters = vec![];
+    let st = TrxStructure::decode(structure);
+    let end = pos + ref_len.max(1) - 1;
+
+    if let Some(st) = st.as_ref() {
+        let truncating = terms.iter().any(|t| *t == "stop_gained" || *t == "frameshift");
+        if truncating {
+            if let Some((c, len)) = st.cds_pos(pos) {
+                if c as f64 > END_TRUNC_FRAC * len as f64 {
+                    filters.push(END_TRUNC);
+                }
+            }
+        }
+    }
+    if unknown_start_end
+        .split('&')
+        .any(|u| u == "cds_start_NF" || u == "cds_end_NF")
+    {
+        filters.push(INCOMPLETE_CDS);
+    }
+    if let Some(st) = st.as_ref() {
+        if st.exons.len() == 1 {
+            filters.push(SINGLE_EXON);
+        }
+        if let Some(fasta) = fasta {
+            if non_canonical_splice(st, chrom, pos, end, fasta) {
+                filters.push(NON_CAN_SPLICE);
+            }
+        }
+    }
+
+    let confidence = match filters.is_empty() {
+        true => "HC",
+        false => "LC",
+    };
+    return Some((confidence, filters));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str;
+
+    // 101bp CDS over two exons, the last 5% starts at CDS position 96, genomic 344
+    const PLUS: &str = "+;100-200,300-400;150-200,300-349";
+    const MINUS: &str = "-;100-200,300-400;150-200,300-349";
+    const SINGLE: &str = "+;100-400;150-349";
+
+    type Expected = Option<(&'static str, Vec<&'static str>)>;
+
+    /// chr1 of 400 A's with the given bases written in, 1-based
+    fn reference(name: &str, bases: &[(u64, &str)]) -> Reference {
+        let mut seq = vec![b'A'; 400];
+        for (pos, b) in bases {
+            seq[*pos as usize - 1..*pos as usize - 1 + b.len()].copy_from_slice(b.as_bytes());
+        }
+        return Reference::from_seq(&format!("lof_{}", name), "chr1", str::from_utf8(&seq).unwrap());
+    }
+
+    #[test]
+    fn confidence_and_filters() {
+        let cases: [(&str, &str, &str, u64, Expected); 12] = [
+            ("missense_variant", "", PLUS, 160, None),
+            // splice region and intron variants are not LoF
+            ("splice_region_variant&intron_variant", "", PLUS, 203, None),
+            ("stop_gained", "", PLUS, 160, Some(("HC", vec![]))),
+            ("*stop_gained", "", PLUS, 160, Some(("HC", vec![]))),
+            ("stop_gained", "", PLUS, 343, Some(("HC", vec![]))),
+            ("stop_gained", "", PLUS, 344, Some(("LC", vec![END_TRUNC]))),
+            ("frameshift_variant", "", PLUS, 349, Some(("LC", vec![END_TRUNC]))),
+            // on the minus strand the CDS ends at 150
+            ("stop_gained", "", MINUS, 154, Some(("LC", vec![END_TRUNC]))),
+            ("stop_gained", "", MINUS, 344, Some(("HC", vec![]))),
+            // END_TRUNC is only for truncating consequences
+            ("splice_donor_variant", "", PLUS, 345, Some(("HC", vec![]))),
+            ("stop_gained", "cds_end_NF", PLUS, 160, Some(("LC", vec![INCOMPLETE_CDS]))),
+            ("stop_gained", "", SINGLE, 160, Some(("LC", vec![SINGLE_EXON]))),
+        ];
+        for (csq, unknown, st, pos, expected) in cases {
+            assert_eq!(
+                lof_confidence(csq, unknown, st, "chr1", pos, 1, None),
+                expected,
+                "{} at {} on {}",
+                csq,
+                pos,
+                st
+            );
+        }
+        // no structure, nothing to judge against
+        assert_eq!(lof_confidence("stop_gained", "", "", "chr1", 160, 1, None), Some(("HC", vec![])));
+    }
+
+    #[test]
+    fn non_canonical_splice_sites() {
+        // intron 201-299, plus strand reads GT..AG
+        let plus = reference("plus", &[(201, "GT"), (298, "AG")]);
+        // minus strand reads CT..AC on the plus strand
+        let minus = reference("minus", &[(201, "CT"), (298, "AC")]);
+        let gc = reference("gc", &[(201, "GC"), (298, "AG")]);
+        let cases: [(&str, &Reference, &str, u64, u64, Expected); 8] = [
+            ("splice_donor_variant", &plus, PLUS, 201, 1, Some(("HC", vec![]))),
+            ("splice_acceptor_variant", &plus, PLUS, 299, 1, Some(("HC", vec![]))),
+            ("splice_donor_variant", &gc, PLUS, 202, 1, Some(("HC", vec![]))),
+            ("splice_donor_variant", &minus, MINUS, 299, 1, Some(("HC", vec![]))),
+            ("splice_acceptor_variant", &minus, MINUS, 201, 1, Some(("HC", vec![]))),
+            // the plus strand's GT..AG read backwards is not canonical
+            ("splice_donor_variant", &plus, MINUS, 299, 1, Some(("LC", vec![NON_CAN_SPLICE]))),
+            ("splice_acceptor_variant", &minus, PLUS, 298, 2, Some(("LC", vec![NON_CAN_SPLICE]))),
+            // a deletion reaching the intron end from the exon is checked too
+            ("splice_donor_variant", &minus, PLUS, 199, 3, Some(("LC", vec![NON_CAN_SPLICE]))),
+        ];
+        for (csq, fasta, st, pos, ref_len, expected) in cases {
+            assert_eq!(
+                lof_confidence(csq, "", st, "chr1", pos, ref_len, Some(fasta)),
+                expected,
+                "{} at {} on {}",
+                csq,
+                pos,
+                st
+            );
+        }
+    }
+}
@@ -0,0 +1,1204 @@
+mod hgvs;
+mod index;
+mod lof;
+mod nmd;
+mod reference;
+pub mod schema;
+
+use bcfutils::genes::{unversioned, Gene, GeneModel};
+use bcfutils::gff::{parse_sub_feature, read_gff_lines, transcript_key, SubFeature, Transcript, TrxStructure};
+use index::TrxIndex;
+use linear_map::LinearMap;
+use reference::Reference;
+use schema::FieldType;
+use rust_htslib::bcf::header::HeaderRecord;
+use rust_htslib::bcf::record::{Buffer, Numeric};
+use rust_htslib::bcf::{Header, Read};
+use std::collections::{BTreeMap, HashMap};
+use std::process;
+use std::str;
+
+/// the appended subfields of a transcript, in schema order
+fn get_appended(trns: &Transcript) -> [String; schema::NUM_APPENDED] {
+    let flag = |tag: &str, val: &str| match trns.has_tag(tag) {
+        true => val.to_string(),
+        false => String::new(),
+    };
+    // unversioned like the gene IDs looked up by symbol for transcripts not in the map
+    return [
+        unversioned(&trns.gene_id).to_string(),
+        flag("Ensembl_canonical", "YES"),
+        trns.appris().unwrap_or("").to_string(),
+        flag("CCDS", "CCDS"),
+        flag("readthrough_transcript", "readthrough_transcript"),
+        trns.uncertain_start_end(),
+        trns.tsl.map_or(String::new(), |t| t.to_string()),
+        trns.versioned_id(),
+        trns.mane().unwrap_or("").to_string(),
+        trns.refseq.clone().unwrap_or_default(),
+    ];
+}
+
+fn add_trns_to_map(trns: Transcript, map: &mut HashMap<String, (String, String)>) {
+    let s = format!("|{}", get_appended(&trns).join("|"));
+    map.insert(trns.transcript_id, (s, String::new()));
+}
+
+/// key gene symbols are stored under in the transcript map, next to the transcript IDs
+const GENE_KEY_PREFIX: &str = "gene:";
+
+/// build the transcript map from a GFF3 or GTF file, plain, gzip, or bgzip compressed. each
+/// transcript maps to its appended subfields and its encoded exon/CDS structure, and each
+/// gene symbol, under GENE_KEY_PREFIX, to its gene ID
+fn build_trx_map(gff_fp: &str) -> HashMap<String, (String, String)> {
+    let mut trns_map: HashMap<String, (String, String)> = HashMap::new();
+    let mut structures: HashMap<String, TrxStructure> = HashMap::new();
+    let mut genes = GeneModel::new();
+
+    let read = read_gff_lines(gff_fp, |l, format| {
+        if let Some(gene) = Gene::from_gff_line(l, format) {
+            genes.add(gene);
+        } else if let Some(trns) = Transcript::from_gff_line(l, format) {
+            genes.add_transcript(&trns);
+            structures
+                .entry(trns.transcript_id.clone())
+                .or_default()
+                .strand = trns.strand;
+            add_trns_to_map(trns, &mut trns_map);
+        } else if let Some((trns_id, kind, s, e)) = parse_sub_feature(l, format) {
+            let st = structures.entry(trns_id).or_default();
+            match kind {
+                SubFeature::Exon => st.exons.push((s, e)),
+                SubFeature::Cds => st.cds.push((s, e)),
+            }
+        }
+    });
+    if let Err(e) = read {
+        eprintln!("Error: unable to read gff file {}: {}", gff_fp, e);
+        process::exit(1);
+    }
+    for (trns_id, (_, st_enc)) in trns_map.iter_mut() {
+        if let Some(st) = structures.get_mut(trns_id) {
+            st.sort();
+            *st_enc = st.encode();
+        }
+    }
+    for gene in genes.iter().filter(|g| !g.gene_name.is_empty()) {
+        trns_map
+            .entry(format!("{}{}", GENE_KEY_PREFIX, gene.gene_name))
+            .or_insert_with(|| (gene.gene_id.clone(), String::new()));
+    }
+    return trns_map;
+}
+
+/// consequence INFO tags mcsq knows how to read, in order of preference
+pub const CSQ_TAGS: [&str; 3] = ["BCSQ", "CSQ", "ANN"];
+
+/// source names for the bcftools csq fields, checked against the BCSQ, VEP CSQ, and SnpEff ANN
+/// Format lists in order
+const BASE_FIELDS: [&[&str]; 7] = [
+    &["Consequence", "Annotation"],
+    &["gene", "SYMBOL", "Gene_Name"],
+    &["transcript", "Feature", "Feature_ID"],
+    &["biotype", "BIOTYPE", "Transcript_BioType"],
+    &["strand", "STRAND"],
+    &["amino_acid_change", "HGVSp", "HGVS.p", "Amino_acids"],
+    &["dna_change", "HGVSc", "HGVS.c", "Codons"],
+];
+
+pub fn get_csq_hdr_map(hdr_recs: Vec<HeaderRecord>) -> Option<(String, LinearMap<String, String>)> {
+    for tag in CSQ_TAGS {
+        for hrec in hdr_recs.iter() {
+            match hrec {
+                HeaderRecord::Info { values, .. } => {
+                    if values.get("ID").unwrap() == tag {
+                        return Some((tag.to_string(), values.clone()));
+                    }
+                }
+                _ => continue,
+            }
+        }
+    }
+    return None;
+}
+
+/// pull the subfield names out of the header Description, ie
+/// BCSQ/CSQ: `... Format: Consequence|gene|...`
+/// ANN: `Functional annotations: 'Allele | Annotation | ...' `
+pub fn get_csq_format(desc: &str) -> Vec<String> {
+    let sb = match desc.find("Format:") {
+        Some(b) => b + 7,
+        None => match desc.find("annotations:") {
+            Some(b) => b + 12,
+            None => 0,
+        },
+    };
+    return desc[sb..]
+        .trim()
+        .trim_matches(|c| c == '\'' || c == '"')
+        .split('|')
+        .map(|f| f.trim().to_string())
+        .collect();
+}
+
+pub fn get_csq_layout(format: &Vec<String>) -> Vec<Option<usize>> {
+    return BASE_FIELDS
+        .iter()
+        .map(|names| {
+            names
+                .iter()
+                .find_map(|name| format.iter().position(|f| f == name))
+        })
+        .collect();
+}
+
+fn normalize_strand(strand: &str) -> &str {
+    return match strand {
+        "1" => "+",
+        "-1" => "-",
+        _ => strand,
+    };
+}
+
+/// rewrite a BCSQ, CSQ or ANN entry into the bcftools csq layout
+/// Consequence|gene|transcript|biotype|strand|amino_acid_change|dna_change
+pub fn normalize_csq(csq: &str, layout: &Vec<Option<usize>>) -> String {
+    let fields = csq.split('|').collect::<Vec<&str>>();
+    let mut norm = vec![];
+    for (i, idx) in layout.iter().enumerate() {
+        let f = match idx {
+            Some(idx) => fields.get(*idx).unwrap_or(&""),
+            None => "",
+        };
+        if i == schema::STRAND {
+            norm.push(normalize_strand(f));
+        } else {
+            norm.push(f);
+        }
+    }
+    return norm.join("|");
+}
+
+fn get_canon_rank(csq: &Vec<&str>) -> u8 {
+    let canon = csq[schema::CANONICAL];
+    return match canon {
+        "YES" => 1,
+        _ => 99,
+    };
+}
+
+fn get_appris_rank(csq: &Vec<&str>) -> u8 {
+    let appris = csq[schema::APPRIS];
+    return match appris {
+        "principal_1" => 1,
+        "principal_2" => 2,
+        "principal_3" => 3,
+        "principal_4" => 4,
+        "principal_5" => 5,
+        "alternative_1" => 6,
+        "alternative_2" => 7,
+        _ => 99,
+    };
+}
+
+fn get_tsl_rank(csq: &Vec<&str>) -> u8 {
+    let tsl = csq[schema::TSL];
+    return match tsl {
+        "1" => 1,
+        "2" => 2,
+        "3" => 3,
+        "4" => 4,
+        "5" => 5,
+        _ => 99,
+    };
+}
+
+/// severity of a single consequence term, 1 is the most severe and 99 is unknown
+pub fn get_term_severity(term: &str) -> u8 {
+    return consequence_terms(term).next().map_or(99, term_severity);
+}
+
+/// severity of a term already stripped by consequence_terms
+fn term_severity(c: &str) -> u8 {
+    return match c {
+        "transcript_ablation" => 1,
+        "gene_fusion" => 1,
+        "bidirectional_gene_fusion" => 1,
+        "splice_acceptor" => 2,
+        "splice_donor" => 2,
+        "stop_gained" => 3,
+        "frameshift" => 3,
+        "stop_lost" => 3,
+        "start_lost" => 3,
+        "disruptive" => 4,
+        "exon_loss" => 5,
+        "transcript_amplification" => 6,
+        "inframe_altering" => 7,
+        "inframe_insertion" => 7,
+        "inframe_deletion" => 7,
+        "missense" => 7,
+        "protein_altering" => 7,
+        "inframe" => 7,
+        "conservative_inframe_insertion" => 7,
+        "conservative_inframe_deletion" => 7,
+        "disruptive_inframe_insertion" => 7,
+        "disruptive_inframe_deletion" => 7,
+        "rare_amino_acid" => 7,
+        "splice_region" => 8,
+        "splice_donor_5th_base" => 8,
+        "splice_donor_region" => 8,
+        "splice_polypyrimidine_tract" => 8,
+        "incomplete_terminal_codon" => 9,
+        "synonymous" => 10,
+        "stop_retained" => 10,
+        "start_retained" => 10,
+        "initiator_codon" => 10,
+        "coding_sequence" => 11,
+        "mature_miRNA" => 11,
+        "5_prime_utr" => 12,
+        "3_prime_utr" => 12,
+        "5_prime_UTR" => 12,
+        "3_prime_UTR" => 12,
+        "5_prime_UTR_premature_start_codon_gain" => 12,
+        "non_coding_transcript_exon" => 13,
+        "intron" => 14,
+        "NMD_transcript" => 14,
+        "non_coding" => 15,
+        "non_coding_transcript" => 15,
+        "intragenic" => 15,
+        "downstream" => 16,
+        "upstream" => 16,
+        "downstream_gene" => 16,
+        "upstream_gene" => 16,
+        "TF_binding_site" => 17,
+        "TFBS" => 17,
+        "TFBS_ablation" => 17,
+        "TFBS_amplification" => 17,
+        "regulatory" => 18,
+        "regulatory_region" => 18,
+        "regulatory_region_ablation" => 18,
+        "regulatory_region_amplification" => 18,
+        "feature_truncation" => 19,
+        "feature_elongation" => 19,
+        "intergenic" => 20,
+        "intergenic_region" => 20,
+        _ => 99,
+    };
+}
+
+/// severity of the worst term in an & joined consequence
+pub fn get_consequence_severity(consequence: &str) -> u8 {
+    return consequence_terms(consequence)
+        .map(term_severity)
+        .min()
+        .unwrap_or(99);
+}
+
+/// the terms of an & joined consequence without the bcftools * prefix or the _variant suffix,
+/// VEP and SnpEff use the full SO term while bcftools drops the suffix
+fn consequence_terms(consequence: &str) -> impl Iterator<Item = &str> {
+    return consequence
+        .split('&')
+        .map(|c| c.trim_start_matches('*').trim_end_matches("_variant"));
+}
+
+fn get_severity_rank(csq: &Vec<&str>) -> u8 {
+    return get_consequence_severity(csq[schema::CONSEQUENCE]);
+}
+
+fn get_mane_rank(csq: &Vec<&str>) -> u8 {
+    return match csq[schema::MANE] {
+        "MANE_Select" => 1,
+        "MANE_Plus_Clinical" => 2,
+        _ => 99,
+    };
+}
+
+fn get_biotype_rank(csq: &Vec<&str>) -> u8 {
+    return match csq[schema::BIOTYPE] {
+        "protein_coding" => 1,
+        _ => 99,
+    };
+}
+
+fn get_readthrough_rank(csq: &Vec<&str>) -> u8 {
+    return match csq[schema::READTHROUGH] {
+        "readthrough_transcript" => 99,
+        _ => 1,
+    };
+}
+
+const CANON: usize = 0;
+const APPRIS: usize = 1;
+const TSL: usize = 2;
+const SEVERE: usize = 3;
+const BIOTYPE: usize = 4;
+const READTHROUGH: usize = 5;
+const MANE: usize = 6;
+const NUM_RANKS: usize = 7;
+
+/// criterion names, indexed by the rank constants above
+const RANK_NAMES: [&str; NUM_RANKS] = [
+    "CANONICAL",
+    "APPRIS",
+    "TSL",
+    "SEVERITY",
+    "BIOTYPE",
+    "READTHROUGH",
+    "MANE",
+];
+
+fn get_ranks(csq: &Vec<&str>) -> [u8; NUM_RANKS] {
+    return [
+        get_canon_rank(csq),
+        get_appris_rank(csq),
+        get_tsl_rank(csq),
+        get_severity_rank(csq),
+        get_biotype_rank(csq),
+        get_readthrough_rank(csq),
+        get_mane_rank(csq),
+    ];
+}
+
+fn compare_ranks(
+    p_rank: &mut [u8; NUM_RANKS],
+    p_csq_idx: &mut usize,
+    c_rank: [u8; NUM_RANKS],
+    c_csq_idx: usize,
+    comps: &[usize],
+) {
+    for &comp in comps {
+        if c_rank[comp] < p_rank[comp] {
+            *p_rank = c_rank;
+            *p_csq_idx = c_csq_idx;
+            return;
+        }
+        if c_rank[comp] > p_rank[comp] {
+            return;
+        }
+    }
+}
+
+/// one set of pick_/canon_/... INFO fields, the rank comparison order used to choose the entry,
+/// and the criterion the chosen entry must have to be reported at all
+struct Strategy {
+    prefix: &'static str,
+    desc: &'static str,
+    comps: &'static [usize],
+    require: Option<usize>,
+}
+
+const STRATEGIES: [Strategy; 5] = [
+    Strategy {
+        prefix: "pick",
+        desc: "picked csq, MANE->canonical->appris->TSL->biotype->severity",
+        comps: &[READTHROUGH, MANE, CANON, APPRIS, TSL, BIOTYPE, SEVERE],
+        require: None,
+    },
+    Strategy {
+        prefix: "canon",
+        desc: "canon",
+        comps: &[CANON, APPRIS, TSL, BIOTYPE, SEVERE],
+        require: Some(CANON),
+    },
+    Strategy {
+        prefix: "mane",
+        desc: "MANE Select, then MANE Plus Clinical",
+        comps: &[MANE, CANON, APPRIS, TSL, BIOTYPE, SEVERE],
+        require: Some(MANE),
+    },
+    Strategy {
+        prefix: "worst",
+        desc: "worst",
+        comps: &[READTHROUGH, SEVERE, CANON, APPRIS, TSL, BIOTYPE],
+        require: None,
+    },
+    Strategy {
+        prefix: "wpc",
+        desc: "worst protein coding",
+        comps: &[READTHROUGH, BIOTYPE, SEVERE, CANON, APPRIS, TSL],
+        require: None,
+    },
+];
+
+fn select_csq(
+    ranks: &Vec<[u8; NUM_RANKS]>,
+    members: &Vec<usize>,
+    strategy: &Strategy,
+) -> Option<usize> {
+    let mut p_csq_idx = *members.first()?;
+    let mut p_rank = ranks[p_csq_idx];
+    for &i in members.iter().skip(1) {
+        compare_ranks(&mut p_rank, &mut p_csq_idx, ranks[i], i, strategy.comps);
+    }
+    return match strategy.require {
+        Some(r) if p_rank[r] == 99 => None,
+        _ => Some(p_csq_idx),
+    };
+}
+
+/// the criterion that separated the picked entry from its closest competitor, `only` when there
+/// was nothing to compare against and `tie` when it won on input order alone
+fn get_pick_reason(
+    ranks: &Vec<[u8; NUM_RANKS]>,
+    members: &Vec<usize>,
+    picked: usize,
+    strategy: &Strategy,
+) -> &'static str {
+    let mut decided: Option<usize> = None;
+    for &i in members.iter().filter(|&&i| i != picked) {
+        match strategy
+            .comps
+            .iter()
+            .position(|&c| ranks[i][c] != ranks[picked][c])
+        {
+            Some(k) => decided = Some(decided.map_or(k, |d| d.max(k))),
+            None => return "tie",
+        }
+    }
+    return match decided {
+        Some(k) => RANK_NAMES[strategy.comps[k]],
+        None => "only",
+    };
+}
+
+fn format_ranks(rank: &[u8; NUM_RANKS]) -> String {
+    return rank
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<String>>()
+        .join("|");
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PickMode {
+    Record,
+    Allele,
+    Gene,
+}
+
+/// ALT alleles as each annotation writes them. VEP drops the leading base shared by indel
+/// alleles and writes '-' for an empty allele, SnpEff and bcftools keep the VCF allele
+fn get_allele_keys(csq_tag: &str, alleles: &Vec<String>) -> Vec<String> {
+    let rf = &alleles[0];
+    let alts = &alleles[1..];
+    let strip = csq_tag == "CSQ"
+        && alts
+            .iter()
+            .all(|a| !a.is_empty() && !rf.is_empty() && a[..1] == rf[..1])
+        && alts.iter().any(|a| a.len() != rf.len());
+    return alts
+        .iter()
+        .map(|a| match (strip, &a[1.min(a.len())..]) {
+            (true, "") => "-".to_string(),
+            (true, s) => s.to_string(),
+            (false, _) => a.clone(),
+        })
+        .collect();
+}
+
+/// the variant at pos out of a BCSQ dna_change, compound ones list every variant of the
+/// haplotype, ie 5530601G>C out of 5530600T>A+5530601G>C
+fn find_dna_change_part(dna_change: &str, pos: i64) -> Option<&str> {
+    return dna_change.split('+').find(|part| {
+        let end = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+        part[..end].parse::<i64>().ok() == Some(pos)
+    });
+}
+
+/// the variant at pos out of a BCSQ dna_change, the first one when none is at pos
+fn get_dna_change_part(dna_change: &str, pos: i64) -> &str {
+    return find_dna_change_part(dna_change, pos)
+        .unwrap_or_else(|| dna_change.split('+').next().unwrap_or(""));
+}
+
+/// allele an entry belongs to, BCSQ has no allele subfield so it comes from the ALT side of
+/// the dna_change, ie 5530600T>A
+fn get_csq_allele<'a>(
+    raw: &'a str,
+    mcsq: &'a str,
+    csq_tag: &str,
+    allele_idx: Option<usize>,
+    pos: i64,
+) -> Option<&'a str> {
+    if csq_tag == "BCSQ" {
+        return mcsq
+            .split('|')
+            .nth(schema::DNA_CHANGE)
+            .and_then(|d| get_dna_change_part(d, pos).split_once('>'))
+            .map(|(_, a)| a);
+    }
+    return raw.split('|').nth(allele_idx?).map(|a| a.trim());
+}
+
+/// split entries into the groups a pick is made in, entries with an allele that cant be matched
+/// are kept in every allele group
+fn group_csqs(
+    mode: PickMode,
+    mcsqs: &Vec<String>,
+    csq_alleles: &Vec<Option<usize>>,
+    num_alts: usize,
+) -> Vec<Vec<usize>> {
+    match mode {
+        PickMode::Record => return vec![(0..mcsqs.len()).collect()],
+        PickMode::Allele => {
+            return (0..num_alts)
+                .map(|a| {
+                    (0..mcsqs.len())
+                        .filter(|&i| csq_alleles[i].is_none_or(|ca| ca == a))
+                        .collect()
+                })
+                .collect()
+        }
+        PickMode::Gene => {
+            let mut genes: Vec<&str> = vec![];
+            let mut groups: Vec<Vec<usize>> = vec![];
+            for (i, mcsq) in mcsqs.iter().enumerate() {
+                let gene = mcsq.split('|').nth(schema::GENE).unwrap_or("");
+                match genes.iter().position(|g| *g == gene) {
+                    Some(gi) => groups[gi].push(i),
+                    None => {
+                        genes.push(gene);
+                        groups.push(vec![i]);
+                    }
+                }
+            }
+            return groups;
+        }
+    }
+}
+
+/// Flags cant be per allele or per gene, those are written as 0/1 Integers instead
+fn get_hdr_number_type(ty: FieldType, mode: PickMode) -> (&'static str, &'static str) {
+    let number = match mode {
+        PickMode::Record => "1",
+        PickMode::Allele => "A",
+        PickMode::Gene => ".",
+    };
+    return match (ty, mode) {
+        (FieldType::Str, _) => (number, "String"),
+        (FieldType::Int, _) => (number, "Integer"),
+        (FieldType::Flag, PickMode::Record) => ("0", "Flag"),
+        (FieldType::Flag, _) => (number, "Integer"),
+    };
+}
+
+/// protein position out of 123P>123L (bcftools) or p.Pro123Leu (HGVS)
+fn get_aa_pos(aa_change: &str) -> Option<&str> {
+    let sb = aa_change.find(|c: char| c.is_ascii_digit())?;
+    let eb = aa_change[sb..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(aa_change.len(), |e| sb + e);
+    return Some(&aa_change[sb..eb]);
+}
+
+fn get_int_value(field: &str, val: &str) -> Option<i32> {
+    return match (field, val) {
+        ("strand", "+") => Some(1),
+        ("strand", "-") => Some(-1),
+        _ => val.parse().ok(),
+    };
+}
+
+/// how far back, in bp, compound BCSQ entries are kept for @pos back-references to find them
+const BACKREF_WINDOW: i64 = 5_000_000;
+
+/// strategies that also get HGVS descriptions with --hgvs
+const HGVS_STRATEGIES: [&str; 2] = ["pick", "canon"];
+
+/// write one picked field, values are None where nothing was picked or the subfield is empty.
+/// fields with no values at all are left off the record
+fn push_pick_field(
+    record: &mut rust_htslib::bcf::Record,
+    tag: &[u8],
+    ty: FieldType,
+    field: &str,
+    vals: &Vec<Option<&str>>,
+    mode: PickMode,
+) {
+    if vals.iter().all(|v| v.is_none()) {
+        return;
+    }
+    match (ty, mode) {
+        (FieldType::Str, _) => {
+            let vals = vals
+                .iter()
+                .map(|v| v.unwrap_or(".").as_bytes())
+                .collect::<Vec<&[u8]>>();
+            record
+                .push_info_string(tag, &vals)
+                .expect("failed to set picked csq field");
+        }
+        (FieldType::Flag, PickMode::Record) => {
+            record
+                .push_info_flag(tag)
+                .expect("failed to set picked csq field");
+        }
+        (FieldType::Int, _) | (FieldType::Flag, _) => {
+            let vals = vals
+                .iter()
+                .map(|v| match (ty, v) {
+                    (FieldType::Flag, Some(_)) => 1,
+                    (FieldType::Flag, None) => 0,
+                    (_, Some(v)) => get_int_value(field, v).unwrap_or(i32::missing()),
+                    (_, None) => i32::missing(),
+                })
+                .collect::<Vec<i32>>();
+            if vals.iter().all(|v| v.is_missing()) {
+                return;
+            }
+            record
+                .push_info_integer(tag, &vals)
+                .expect("failed to set picked csq field");
+        }
+    }
+}
+
+/// transcript fields to append, parsed from the gff or read from a prebuilt index
+enum TrxTable {
+    Map(HashMap<String, (String, String)>),
+    Index(TrxIndex),
+}
+
+impl TrxTable {
+    /// appended subfields and encoded exon/CDS structure of a transcript
+    fn get(&self, trn: &str) -> Option<(&str, &str)> {
+        return match self {
+            TrxTable::Map(m) => m.get(trn).map(|(t, st)| (t.as_str(), st.as_str())),
+            TrxTable::Index(i) => i.get(trn),
+        };
+    }
+
+    /// unversioned Ensembl gene ID of a gene symbol
+    fn gene_id(&self, gene: &str) -> Option<&str> {
+        return self
+            .get(&format!("{}{}", GENE_KEY_PREFIX, gene))
+            .map(|(g, _)| g);
+    }
+}
+
+fn load_trx_table(gff_fp: Option<&str>, trx_index: Option<&str>) -> TrxTable {
+    match (gff_fp, trx_index) {
+        (None, None) => {
+            eprintln!("Error: need to specify a GFF3 or GTF file with --gff, or an index with --trx-index");
+            process::exit(1);
+        }
+        (Some(gff_fp), None) => return TrxTable::Map(build_trx_map(gff_fp)),
+        (gff_fp, Some(trx_index)) => {
+            let idx = match TrxIndex::open(trx_index) {
+                Ok(i) => i,
+                Err(e) => {
+                    eprintln!("Error: unable to open transcript index: {}", e);
+                    process::exit(1);
+                }
+            };
+            // only pay for the checksum if the gff was given to check against
+            if let Some(gff_fp) = gff_fp {
+                let checksum = index::checksum_file(gff_fp).expect("unable to read gff file");
+                if checksum != idx.checksum {
+                    eprintln!(
+                        "Error: {} was not built from {}, rebuild it with `mcsq build-index`",
+                        trx_index, gff_fp
+                    );
+                    process::exit(1);
+                }
+            }
+            return TrxTable::Index(idx);
+        }
+    }
+}
+
+pub fn build_index(gff_fp: Option<&str>, output: Option<&str>) {
+    let (gff_fp, output) = match (gff_fp, output) {
+        (Some(g), Some(o)) => (g, o),
+        _ => {
+            eprintln!("Error: need to specify a GFF3 or GTF file with --gff and the index path with --output");
+            process::exit(1);
+        }
+    };
+    let trx_map = build_trx_map(gff_fp);
+    let checksum = index::checksum_file(gff_fp).expect("unable to read gff file");
+    index::write_index(&trx_map, checksum, output).expect("failed to write transcript index");
+}
+
+pub fn mcsq(
+    input: Option<&str>,
+    output: Option<&str>,
+    gff_fp: Option<&str>,
+    trx_index: Option<&str>,
+    per_allele: &bool,
+    per_gene: &bool,
+    emit_ranks: &bool,
+    explain: Option<&str>,
+    lof: &bool,
+    nmd: &bool,
+    hgvs: &bool,
+    fasta: Option<&str>,
+    validate: &bool,
+    threads: &usize,
+) {
+    let trx_map = load_trx_table(gff_fp, trx_index);
+    if *hgvs && fasta.is_none() {
+        eprintln!("Error: --hgvs needs the reference fasta given with --fasta");
+        process::exit(1);
+    }
+    let fasta = fasta.map(|fp| match Reference::open(fp) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: unable to open reference fasta {}: {}", fp, e);
+            process::exit(1);
+        }
+    });
+    let mut cds_cache = hgvs::CdsCache::default();
+    let mode = match (per_allele, per_gene) {
+        (true, _) => PickMode::Allele,
+        (_, true) => PickMode::Gene,
+        _ => PickMode::Record,
+    };
+
+    let mut bcf = bcfutils::get_rdr(input);
+    bcf.set_threads(threads.clone())
+        .expect("unable to set reader threads");
+
+    let hdrv = bcf.header();
+
+    let (csq_tag, csq_map) = get_csq_hdr_map(hdrv.header_records())
+        .expect("was not able to get BCSQ, CSQ or ANN header info line");
+    let format = get_csq_format(csq_map.get("Description").expect("csq map doesnt have \"Description\", it really should though, something funky is happening"));
+    let layout = get_csq_layout(&format);
+    let allele_idx = format.iter().position(|f| f == "Allele");
+    if layout[0].is_none() || layout[2].is_none() {
+        eprintln!(
+            "Error: could not find consequence and transcript subfields in {} header Description",
+            csq_tag
+        );
+        process::exit(1);
+    }
+
+    let mut hdr = Header::from_template(&hdrv);
+
+    // only BCSQ is rewritten with the appended fields, CSQ and ANN are passed through as is
+    if csq_tag == "BCSQ" {
+        hdr.remove_info(b"BCSQ");
+        hdr.push_record(format!("##INFO=<ID=BCSQ,Number=.,Type=String,Description=\"Local consequence annotation from BCFtools/csq, see http://samtools.github.io/bcftools/howtos/csq-calling.html for details. Format: {}\">", schema::format_string()).as_bytes());
+    }
+
+    let per = match mode {
+        PickMode::Record => "",
+        PickMode::Allele => " per ALT allele",
+        PickMode::Gene => " per overlapping gene",
+    };
+    for (new_field, ty) in schema::SUBFIELDS.iter().chain(schema::DERIVED_FIELDS.iter()) {
+        let (number, ty) = get_hdr_number_type(*ty, mode);
+        for strategy in STRATEGIES.iter() {
+            hdr.push_record(format!("##INFO=<ID={}_{},Number={},Type={},Description=\"{} {}{}\">", strategy.prefix, new_field, number, ty, strategy.desc, new_field, per).as_bytes());
+        }
+    }
+    if *emit_ranks {
+        let (number, _) = get_hdr_number_type(FieldType::Str, mode);
+        for strategy in STRATEGIES.iter() {
+            hdr.push_record(format!("##INFO=<ID={}_ranks,Number={},Type=String,Description=\"ranks of the {} entry{}, Format: {}, lower is better and 99 is unranked\">", strategy.prefix, number, strategy.desc, per, RANK_NAMES.join("|")).as_bytes());
+            hdr.push_record(format!("##INFO=<ID={}_reason,Number={},Type=String,Description=\"criterion that decided the {} entry{}, only when there was a single candidate, tie when it won on input order\">", strategy.prefix, number, strategy.desc, per).as_bytes());
+        }
+    }
+
+    if *lof {
+        let (number, _) = get_hdr_number_type(FieldType::Str, mode);
+        hdr.push_record(format!("##INFO=<ID=pick_LoF,Number={},Type=String,Description=\"LOFTEE style confidence of a predicted LoF pick{}, HC or LC\">", number, per).as_bytes());
+        hdr.push_record(format!("##INFO=<ID=pick_LoF_filter,Number={},Type=String,Description=\"filters that made a predicted LoF pick{} low confidence, & separated, from END_TRUNC, INCOMPLETE_CDS, SINGLE_EXON and NON_CAN_SPLICE (needs --fasta)\">", number, per).as_bytes());
+    }
+
+    if *nmd {
+        let (number, _) = get_hdr_number_type(FieldType::Str, mode);
+        hdr.push_record(format!("##INFO=<ID=pick_NMD,Number={},Type=String,Description=\"whether the transcript of a PTC creating pick{} escapes nonsense mediated decay, escape or triggered\">", number, per).as_bytes());
+    }
+
+    if *hgvs {
+        let (number, _) = get_hdr_number_type(FieldType::Str, mode);
+        for strategy in STRATEGIES.iter().filter(|s| HGVS_STRATEGIES.contains(&s.prefix)) {
+            hdr.push_record(format!("##INFO=<ID={}_HGVSc,Number={},Type=String,Description=\"HGVS coding DNA change of the {} entry{}\">", strategy.prefix, number, strategy.desc, per).as_bytes());
+            hdr.push_record(format!("##INFO=<ID={}_HGVSp,Number={},Type=String,Description=\"HGVS protein change of the {} entry{}, 3-letter amino acids\">", strategy.prefix, number, strategy.desc, per).as_bytes());
+        }
+    }
+
+    let mut obcf = bcfutils::get_wrtr(output, &hdr);
+    obcf.set_threads(threads.clone())
+        .expect("unable to set writer threads");
+
+    let mut explain_wtr = explain.map(|fp| {
+        let mut w = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .from_path(fp)
+            .expect("unable to create explain tsv");
+        let mut hdr_row = vec!["CHROM", "POS", "REF", "ALT", "group", "transcript", "Consequence"];
+        hdr_row.extend(RANK_NAMES.iter());
+        hdr_row.push("picked_by");
+        w.write_record(&hdr_row).expect("failed to write explain header");
+        w
+    });
+
+    let placeholder = schema::placeholder();
+    // compound BCSQ entries of recent records on this chromosome, by 1-based position, for
+    // the @pos back-references of the later variants in the same haplotype
+    let mut compounds: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+    let mut compounds_rid = None;
+    let mut b = Buffer::new();
+    for record_result in bcf.records() {
+        let mut record = record_result.expect("fail to read record");
+        obcf.translate(&mut record);
+        let allele_keys = get_allele_keys(
+            &csq_tag,
+            &record
+                .alleles()
+                .iter()
+                .map(|a| str::from_utf8(a).unwrap().to_string())
+                .collect(),
+        );
+        let bcsqs = match record
+            .info_shared_buffer(csq_tag.as_bytes(), &mut b)
+            .string()
+            .unwrap()
+        {
+            Some(b) => b,
+            None => {
+                obcf.write(&record).expect("failed to write record");
+                continue;
+            }
+        };
+
+        let pos = record.pos() + 1;
+        if compounds_rid != record.rid() {
+            compounds.clear();
+            compounds_rid = record.rid();
+        }
+        compounds = compounds.split_off(&(pos - BACKREF_WINDOW));
+
+        // candidates for picking, and the entries written back out. @pos back-references are
+        // written as they are, the compound entries they point to are picked from in their place
+        let mut mcsqs = vec![];
+        let mut emitted = vec![];
+        let mut new_compounds = vec![];
+
+        for bcsq_b in bcsqs.iter() {
+            let bcsq = str::from_utf8(bcsq_b).unwrap();
+            if let Some(ref_pos) = bcsq.strip_prefix('@') {
+                emitted.push(bcsq.to_string());
+                let resolved = ref_pos
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|p| compounds.get(&p))
+                    .map_or(vec![], |c| {
+                        // only the compound entries this variant is part of
+                        c.iter()
+                            .filter(|m| {
+                                m.split('|')
+                                    .nth(schema::DNA_CHANGE)
+                                    .and_then(|d| find_dna_change_part(d, pos))
+                                    .is_some()
+                            })
+                            .map(|m| (bcsq, m.clone()))
+                            .collect::<Vec<(&str, String)>>()
+                    });
+                mcsqs.extend(resolved);
+                continue;
+            }
+            let mut mcsq = normalize_csq(bcsq, &layout);
+            // VEP and SnpEff report versioned transcript IDs, the map is keyed without version
+            let trn = transcript_key(mcsq.split('|').nth(schema::TRANSCRIPT).unwrap());
+            match trx_map.get(&trn) {
+                Some((t, _)) => mcsq.push_str(t),
+                // the gene can still be known when the transcript isn't, ie from another release
+                None => match trx_map.gene_id(mcsq.split('|').nth(schema::GENE).unwrap()) {
+                    Some(g) => mcsq.push_str(&format!("|{}{}", g, &placeholder[1..])),
+                    None => mcsq.push_str(&placeholder),
+                },
+            }
+            if mcsq
+                .split('|')
+                .nth(schema::DNA_CHANGE)
+                .is_some_and(|d| d.contains('+'))
+            {
+                new_compounds.push(mcsq.clone());
+            }
+            emitted.push(mcsq.clone());
+            mcsqs.push((bcsq, mcsq));
+        }
+        if !new_compounds.is_empty() {
+            compounds.insert(pos, new_compounds);
+        }
+
+        let mut ranks = vec![];
+        let mut csq_alleles = vec![];
+        let mut structures = vec![];
+        for (bcsq, mcsq) in mcsqs.iter() {
+            let trn = transcript_key(mcsq.split('|').nth(schema::TRANSCRIPT).unwrap());
+            structures.push(trx_map.get(&trn).map_or("", |(_, st)| st));
+            ranks.push(get_ranks(&mcsq.split("|").collect::<Vec<&str>>()));
+            csq_alleles.push(
+                get_csq_allele(bcsq, mcsq, &csq_tag, allele_idx, pos)
+                    .and_then(|a| allele_keys.iter().position(|k| k == a)),
+            );
+        }
+        let mcsqs = mcsqs.into_iter().map(|(_, m)| m).collect::<Vec<String>>();
+
+        if *validate {
+            if let Some(bad) = emitted.iter().find(|m| !schema::is_valid(m)) {
+                let rid = record.rid().expect("record has no CHROM");
+                eprintln!(
+                    "Error: {}:{} has a {} entry with {} subfields, the header declares {}: {}",
+                    str::from_utf8(record.header().rid2name(rid).unwrap()).unwrap(),
+                    record.pos() + 1,
+                    csq_tag,
+                    bad.split('|').count(),
+                    schema::SUBFIELDS.len(),
+                    bad
+                );
+                process::exit(1);
+            }
+        }
+
+        let groups = group_csqs(mode, &mcsqs, &csq_alleles, allele_keys.len());
+        let mcsq_fields = mcsqs
+            .iter()
+            .map(|m| {
+                let mut f = m.split('|').collect::<Vec<&str>>();
+                f.push(get_aa_pos(f[schema::AMINO_ACID_CHANGE]).unwrap_or(""));
+                f
+            })
+            .collect::<Vec<Vec<&str>>>();
+        let all_picks = STRATEGIES
+            .iter()
+            .map(|strategy| {
+                groups
+                    .iter()
+                    .map(|g| select_csq(&ranks, g, strategy))
+                    .collect::<Vec<Option<usize>>>()
+            })
+            .collect::<Vec<Vec<Option<usize>>>>();
+
+        if let Some(ewtr) = explain_wtr.as_mut() {
+            let rid = record.rid().expect("record has no CHROM");
+            let chrom = str::from_utf8(record.header().rid2name(rid).unwrap()).unwrap();
+            let pos = (record.pos() + 1).to_string();
+            let alleles = record.alleles();
+            let rf = str::from_utf8(alleles[0]).unwrap();
+            let alts = alleles[1..]
+                .iter()
+                .map(|a| str::from_utf8(a).unwrap())
+                .collect::<Vec<&str>>();
+            for (gi, g) in groups.iter().enumerate() {
+                let group = match mode {
+                    PickMode::Record => ".",
+                    PickMode::Allele => alts[gi],
+                    PickMode::Gene => mcsq_fields[g[0]][schema::GENE],
+                };
+                for &i in g {
+                    let mut picked_by = vec![];
+                    for (si, strategy) in STRATEGIES.iter().enumerate() {
+                        if all_picks[si][gi] == Some(i) {
+                            picked_by.push(format!(
+                                "{}:{}",
+                                strategy.prefix,
+                                get_pick_reason(&ranks, g, i, strategy)
+                            ));
+                        }
+                    }
+                    let mut row = vec![
+                        chrom.to_string(),
+                        pos.clone(),
+                        rf.to_string(),
+                        alts.join(","),
+                        group.to_string(),
+                        mcsq_fields[i][schema::TRANSCRIPT].to_string(),
+                        mcsq_fields[i][schema::CONSEQUENCE].to_string(),
+                    ];
+                    row.extend(ranks[i].iter().map(|r| r.to_string()));
+                    row.push(picked_by.join(","));
+                    ewtr.write_record(&row).expect("failed to write explain row");
+                }
+            }
+        }
+
+        for (si, strategy) in STRATEGIES.iter().enumerate() {
+            let picks = &all_picks[si];
+            if picks.iter().all(|p| p.is_none()) {
+                continue;
+            }
+            if *emit_ranks {
+                let rank_vals = picks
+                    .iter()
+                    .map(|p| p.map(|p| format_ranks(&ranks[p])))
+                    .collect::<Vec<Option<String>>>();
+                push_pick_field(
+                    &mut record,
+                    format!("{}_ranks", strategy.prefix).as_bytes(),
+                    FieldType::Str,
+                    "ranks",
+                    &rank_vals.iter().map(|r| r.as_deref()).collect(),
+                    mode,
+                );
+                let reasons = picks
+                    .iter()
+                    .zip(groups.iter())
+                    .map(|(p, g)| p.map(|p| get_pick_reason(&ranks, g, p, strategy)))
+                    .collect::<Vec<Option<&str>>>();
+                push_pick_field(
+                    &mut record,
+                    format!("{}_reason", strategy.prefix).as_bytes(),
+                    FieldType::Str,
+                    "reason",
+                    &reasons,
+                    mode,
+                );
+            }
+            for (i, (field, ty)) in schema::SUBFIELDS
+                .iter()
+                .chain(schema::DERIVED_FIELDS.iter())
+                .enumerate()
+            {
+                let vals = picks
+                    .iter()
+                    .map(|p| p.map(|p| mcsq_fields[p][i]).filter(|v| !v.is_empty()))
+                    .collect::<Vec<Option<&str>>>();
+                push_pick_field(
+                    &mut record,
+                    format!("{}_{}", strategy.prefix, field).as_bytes(),
+                    *ty,
+                    field,
+                    &vals,
+                    mode,
+                );
+            }
+        }
+
+        if *lof {
+            let rid = record.rid().expect("record has no CHROM");
+            let chrom = str::from_utf8(record.header().rid2name(rid).unwrap())
+                .unwrap()
+                .to_string();
+            let pos = (record.pos() + 1) as u64;
+            let ref_len = record.alleles()[0].len() as u64;
+            let lofs = all_picks[0]
+                .iter()
+                .map(|p| {
+                    p.and_then(|p| {
+                        lof::lof_confidence(
+                            mcsq_fields[p][schema::CONSEQUENCE],
+                            mcsq_fields[p][schema::UNKNOWN_START_END],
+                            structures[p],
+                            &chrom,
+                            pos,
+                            ref_len,
+                            fasta.as_ref(),
+                        )
+                    })
+                })
+                .collect::<Vec<Option<(&str, Vec<&str>)>>>();
+            let lof_filters = lofs
+                .iter()
+                .map(|l| l.as_ref().map(|(_, f)| f.join("&")))
+                .collect::<Vec<Option<String>>>();
+            push_pick_field(
+                &mut record,
+                b"pick_LoF",
+                FieldType::Str,
+                "LoF",
+                &lofs.iter().map(|l| l.as_ref().map(|(c, _)| *c)).collect(),
+                mode,
+            );
+            push_pick_field(
+                &mut record,
+                b"pick_LoF_filter",
+                FieldType::Str,
+                "LoF_filter",
+                &lof_filters
+                    .iter()
+                    .map(|f| f.as_deref().filter(|f| !f.is_empty()))
+                    .collect(),
+                mode,
+            );
+        }
+
+        if *nmd {
+            let pos = (record.pos() + 1) as u64;
+            let nmds = all_picks[0]
+                .iter()
+                .map(|p| p.and_then(|p| nmd::nmd_prediction(mcsq_fields[p][schema::CONSEQUENCE], structures[p], pos)))
+                .collect::<Vec<Option<&str>>>();
+            push_pick_field(&mut record, b"pick_NMD", FieldType::Str, "NMD", &nmds, mode);
+        }
+
+        if *hgvs {
+            let fasta = fasta.as_ref().unwrap();
+            let rid = record.rid().expect("record has no CHROM");
+            let chrom = str::from_utf8(record.header().rid2name(rid).unwrap())
+                .unwrap()
+                .to_string();
+            let pos = (record.pos() + 1) as u64;
+            let alleles = record
+                .alleles()
+                .iter()
+                .map(|a| str::from_utf8(a).unwrap().to_string())
+                .collect::<Vec<String>>();
+            for (si, strategy) in STRATEGIES.iter().enumerate() {
+                if !HGVS_STRATEGIES.contains(&strategy.prefix) {
+                    continue;
+                }
+                let descs = all_picks[si]
+                    .iter()
+                    .enumerate()
+                    .map(|(gi, p)| {
+                        let p = (*p)?;
+                        // per allele groups are one ALT each, otherwise go by the entry's allele
+                        let alt_idx = match (mode, csq_alleles[p], alleles.len()) {
+                            (PickMode::Allele, _, _) => gi,
+                            (_, Some(a), _) => a,
+                            (_, None, 2) => 0,
+                            _ => return None,
+                        };
+                        let trn = match mcsq_fields[p][schema::TRANSCRIPT_ID] {
+                            "" => mcsq_fields[p][schema::TRANSCRIPT],
+                            t => t,
+                        };
+                        Some(hgvs::hgvs(
+                            trn,
+                            structures[p],
+                            &chrom,
+                            pos,
+                            &alleles[0],
+                            &alleles[alt_idx + 1],
+                            fasta,
+                            &mut cds_cache,
+                        ))
+                    })
+                    .collect::<Vec<Option<(Option<String>, Option<String>)>>>();
+                for (field, i) in [("HGVSc", 0), ("HGVSp", 1)] {
+                    let vals = descs
+                        .iter()
+                        .map(|d| {
+                            d.as_ref().and_then(|(c, p)| match i {
+                                0 => c.as_deref(),
+                                _ => p.as_deref(),
+                            })
+                        })
+                        .collect::<Vec<Option<&str>>>();
+                    push_pick_field(
+                        &mut record,
+                        format!("{}_{}", strategy.prefix, field).as_bytes(),
+                        FieldType::Str,
+                        field,
+                        &vals,
+                        mode,
+                    );
+                }
+            }
+        }
+
+        if csq_tag == "BCSQ" {
+            record
+                .push_info_string(b"BCSQ", &[emitted.join(",").as_bytes()])
+                .expect("failed to set BCSQ info field");
+        }
+        obcf.write(&record).expect("failed to write record");
+    }
+}
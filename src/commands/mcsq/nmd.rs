@@ -0,0 +1,67 @@
+use super::consequence_terms;
+use bcfutils::gff::TrxStructure;
+
+/// a PTC within this many bases upstream of the last exon-exon junction escapes NMD
+const JUNCTION_DIST: u64 = 50;
+/// PTCs this close to the start codon escape through reinitiation
+const START_PROXIMAL: u64 = 150;
+
+fn creates_ptc(consequence: &str) -> bool {
+    return consequence_terms(consequence).any(|c| c == "stop_gained" || c == "frameshift");
+}
+
+/// escape or triggered for PTC creating consequences, None otherwise or when the variant
+/// isn't in the coding exons of the transcript. frameshifts are judged at the variant
+/// position since the new stop isn't known without the sequence
+pub fn nmd_prediction(consequence: &str, structure: &str, pos: u64) -> Option<&'static str> {
+    if !creates_ptc(consequence) {
+        return None;
+    }
+    let st = TrxStructure::decode(structure)?;
+    let (c, _) = st.cds_pos(pos)?;
+    let (tx, exon) = st.exon_pos(pos)?;
+    if c <= START_PROXIMAL || exon == st.exons.len() - 1 {
+        return Some("escape");
+    }
+    return match st.last_junction() {
+        Some(j) if j - tx >= JUNCTION_DIST => Some("triggered"),
+        _ => Some("escape"),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the last junction is after transcript position 400 on both strands
+    const PLUS: &str = "+;1-300,401-500,601-700;1-300,401-500,601-650";
+    const MINUS: &str = "-;1-100,201-300,401-700;51-100,201-300,401-700";
+
+    #[test]
+    fn escape_and_trigger_boundaries() {
+        let cases: [(&str, &str, u64, Option<&str>); 16] = [
+            ("missense_variant", PLUS, 200, None),
+            ("intron_variant", PLUS, 350, None),
+            // not in the CDS
+            ("stop_gained", PLUS, 350, None),
+            ("stop_gained", PLUS, 680, None),
+            // the first 150 CDS bases escape by reinitiation
+            ("stop_gained", PLUS, 150, Some("escape")),
+            ("stop_gained", PLUS, 151, Some("triggered")),
+            // 50bp or more before the last junction triggers
+            ("stop_gained", PLUS, 450, Some("triggered")),
+            ("frameshift_variant", PLUS, 451, Some("escape")),
+            ("*stop_gained", PLUS, 450, Some("triggered")),
+            ("stop_gained", PLUS, 610, Some("escape")),
+            ("stop_gained", MINUS, 550, Some("triggered")),
+            ("stop_gained", MINUS, 551, Some("escape")),
+            ("stop_gained", MINUS, 251, Some("triggered")),
+            ("frameshift", MINUS, 250, Some("escape")),
+            ("stop_gained", MINUS, 60, Some("escape")),
+            ("stop_gained", "+;1-700;1-650", 300, Some("escape")),
+        ];
+        for (csq, st, pos, expected) in cases {
+            assert_eq!(nmd_prediction(csq, st, pos), expected, "{} at {} on {}", csq, pos, st);
+        }
+    }
+}
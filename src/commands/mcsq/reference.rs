@@ -0,0 +1,76 @@
+use rust_htslib::faidx;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// indexed reference fasta. faidx hands back a NULL for contigs it doesn't have, so the
+/// contig lengths are read from the .fai to check requests before they get to htslib
+pub struct Reference {
+    rdr: faidx::Reader,
+    lens: HashMap<String, u64>,
+    /// a fasta written by from_seq, removed with its .fai on drop
+    tmp: Option<PathBuf>,
+}
+
+impl Reference {
+    pub fn open(fp: &str) -> Result<Reference, Box<dyn Error>> {
+        // builds the .fai when there isn't one yet
+        let rdr = faidx::Reader::from_path(fp)?;
+        let mut lens = HashMap::new();
+        for l in fs::read_to_string(format!("{}.fai", fp))?.lines() {
+            let mut cols = l.split('\t');
+            if let (Some(name), Some(len)) = (cols.next(), cols.next()) {
+                lens.insert(name.to_string(), len.parse()?);
+            }
+        }
+        return Ok(Reference { rdr, lens, tmp: None });
+    }
+
+    /// uppercased sequence of chrom:start-end, 1-based inclusive, None when out of range
+    pub fn fetch(&self, chrom: &str, start: u64, end: u64) -> Option<String> {
+        let len = *self.lens.get(chrom)?;
+        if start == 0 || start > end || end > len {
+            return None;
+        }
+        return self
+            .rdr
+            .fetch_seq_string(chrom, (start - 1) as usize, (end - 1) as usize)
+            .ok()
+            .map(|s| s.to_ascii_uppercase());
+    }
+
+    /// a one contig fasta written to the temp dir
+    #[cfg(test)]
+    pub fn from_seq(name: &str, chrom: &str, seq: &str) -> Reference {
+        let fp = std::env::temp_dir().join(format!("bcfutils_{}_{}.fa", name, std::process::id()));
+        fs::write(&fp, format!(">{}\n{}\n", chrom, seq)).unwrap();
+        let mut fasta = Reference::open(fp.to_str().unwrap()).unwrap();
+        fasta.tmp = Some(fp);
+        return fasta;
+    }
+}
+
+impl Drop for Reference {
+    fn drop(&mut self) {
+        if let Some(fp) = &self.tmp {
+            let _ = fs::remove_file(fp);
+            let _ = fs::remove_file(format!("{}.fai", fp.display()));
+        }
+    }
+}
+
+/// reverse complement, anything but ACGT becomes N
+pub fn revcomp(seq: &str) -> String {
+    return seq
+        .chars()
+        .rev()
+        .map(|c| match c {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            'T' => 'A',
+            _ => 'N',
+        })
+        .collect();
+}
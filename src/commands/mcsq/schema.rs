@@ -0,0 +1,73 @@
+// the one definition of the subfields mcsq entries carry, the BCSQ header Format, the
+// placeholder for transcripts missing from the gff, and the picked INFO fields all come from here
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Str,
+    Int,
+    Flag,
+}
+
+/// subfields of a rewritten entry, the bcftools csq layout followed by the ones appended from the gff
+pub const SUBFIELDS: [(&str, FieldType); 17] = [
+    ("Consequence", FieldType::Str),
+    ("gene", FieldType::Str),
+    ("transcript", FieldType::Str),
+    ("biotype", FieldType::Str),
+    ("strand", FieldType::Int),
+    ("amino_acid_change", FieldType::Str),
+    ("dna_change", FieldType::Str),
+    ("gene_id", FieldType::Str),
+    ("CANONICAL", FieldType::Flag),
+    ("appris", FieldType::Str),
+    ("ccds", FieldType::Flag),
+    ("readthrough", FieldType::Flag),
+    ("unknown_start_end", FieldType::Str),
+    ("TSL", FieldType::Int),
+    ("transcript_id", FieldType::Str),
+    ("MANE", FieldType::Str),
+    ("MANE_RefSeq", FieldType::Str),
+];
+
+/// picked INFO fields worked out from the subfields, they follow the subfields in the pick output
+pub const DERIVED_FIELDS: [(&str, FieldType); 1] = [("amino_acid_pos", FieldType::Int)];
+
+pub const NUM_BASE: usize = 7;
+pub const NUM_APPENDED: usize = SUBFIELDS.len() - NUM_BASE;
+
+pub const CONSEQUENCE: usize = 0;
+pub const GENE: usize = 1;
+pub const TRANSCRIPT: usize = 2;
+pub const BIOTYPE: usize = 3;
+pub const STRAND: usize = 4;
+pub const AMINO_ACID_CHANGE: usize = 5;
+pub const DNA_CHANGE: usize = 6;
+pub const CANONICAL: usize = 8;
+pub const APPRIS: usize = 9;
+pub const READTHROUGH: usize = 11;
+pub const UNKNOWN_START_END: usize = 12;
+pub const TSL: usize = 13;
+pub const TRANSCRIPT_ID: usize = 14;
+pub const MANE: usize = 15;
+
+/// Format of the rewritten BCSQ header Description
+pub fn format_string() -> String {
+    return SUBFIELDS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join("|");
+}
+
+/// appended to entries whose transcript isn't in the gff, so they still line up
+pub fn placeholder() -> String {
+    return "|".repeat(NUM_APPENDED);
+}
+
+/// true when an entry has exactly the declared subfields, or is a bcftools @pos back-reference
+pub fn is_valid(entry: &str) -> bool {
+    if let Some(pos) = entry.strip_prefix('@') {
+        return pos.parse::<u64>().is_ok();
+    }
+    return entry.split('|').count() == SUBFIELDS.len();
+}
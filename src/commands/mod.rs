@@ -1,5 +1,8 @@
 pub mod fam_freq;
 pub mod ann_qd;
 pub mod mcsq;
+pub mod csq_filter;
 pub mod mnv;
 pub mod glkup;
+pub mod pheno_rank;
+pub mod vlkup;
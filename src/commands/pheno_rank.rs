@@ -0,0 +1,167 @@
+use bcfutils::dbnsfp::{open_dbnsfp, read_dbnsfp, split_dbnsfp};
+use bcfutils::genes::{resolve_gene, GeneModel};
+use bcfutils::hpo::{Ontology, Resnik};
+use bcfutils::read_list;
+use rust_htslib::bcf::record::Buffer;
+use rust_htslib::bcf::{Header, Read};
+use std::collections::{HashMap, HashSet};
+use std::process;
+use std::str;
+
+/// best-match average of the patient terms against a gene's terms, and the patient terms the
+/// gene is annotated with or below
+fn score_gene(
+    resnik: &mut Resnik,
+    ont: &Ontology,
+    patient: &Vec<usize>,
+    gene_terms: &Vec<usize>,
+) -> (f64, Vec<usize>) {
+    let score = resnik.best_match_average(patient, gene_terms);
+    let closure = gene_terms
+        .iter()
+        .flat_map(|t| ont.ancestors(*t))
+        .collect::<HashSet<usize>>();
+    let matched = patient
+        .iter()
+        .filter(|p| closure.contains(p))
+        .copied()
+        .collect();
+    return (score, matched);
+}
+
+pub fn pheno_rank(
+    input: Option<&str>,
+    output: Option<&str>,
+    fields: Option<&str>,
+    hpo: Option<&str>,
+    obo: Option<&str>,
+    dbnsfp_table: Option<&str>,
+    dbnsfp_sep: &str,
+    gff: Option<&str>,
+    threads: &usize,
+) {
+    let (fields, obo) = match (fields, obo) {
+        (Some(f), Some(o)) => (f, o),
+        _ => {
+            eprintln!("Error: need --fields with the gene INFO fields and --obo with the HPO ontology");
+            process::exit(1);
+        }
+    };
+    let ont = match Ontology::from_obo(obo) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("Error: unable to read obo file {}: {}", obo, e);
+            process::exit(1);
+        }
+    };
+    let mut patient = vec![];
+    let mut hpo_terms = read_list(hpo)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<Vec<String>>();
+    hpo_terms.sort();
+    for t in &hpo_terms {
+        match ont.get(t) {
+            Some(i) if !patient.contains(&i) => patient.push(i),
+            Some(_) => {}
+            None => {
+                eprintln!("Error: {} isn't in {}", t, obo);
+                process::exit(1);
+            }
+        }
+    }
+    if patient.is_empty() {
+        eprintln!("Error: need the patient's HPO terms with --hpo");
+        process::exit(1);
+    }
+
+    let (dtab, syns) = match open_dbnsfp(dbnsfp_table) {
+        Some(t) => read_dbnsfp(t, true),
+        None => {
+            eprintln!("Error: no dbNSFP gene table for the gene HPO annotations, pass --dbnsfp-table or build with --features embedded-tables");
+            process::exit(1);
+        }
+    };
+    // terms the ontology doesn't know, usually from a different HPO release, are dropped
+    let annotations = (0..dtab.genes.len())
+        .map(|row| {
+            split_dbnsfp(dtab.get(row, "HPO_id").unwrap_or("."), dbnsfp_sep)
+                .iter()
+                .filter_map(|t| ont.get(t))
+                .collect::<Vec<usize>>()
+        })
+        .collect::<Vec<Vec<usize>>>();
+    let mut resnik = Resnik::new(&ont, &annotations);
+    let model = gff.map(|fp| match GeneModel::from_gff(fp) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Error: unable to read gff file {}: {}", fp, e);
+            process::exit(1);
+        }
+    });
+
+    let mut bcf = bcfutils::get_rdr(input);
+    bcf.set_threads(threads.clone())
+        .expect("unable to set reader threads");
+    let hdrv = bcf.header();
+    let mut hdr = Header::from_template(&hdrv);
+    hdr.push_record(format!("##pheno_rank_hpo={}", hpo_terms.join(",")).as_bytes());
+    hdr.push_record(format!("##INFO=<ID=hpo_gene,Number=1,Type=String,Description=\"gene from {} INFO fields whose dbNSFP HPO_id terms best match the patient terms\">", fields).as_bytes());
+    hdr.push_record(r#"##INFO=<ID=hpo_score,Number=1,Type=Float,Description="Resnik best-match average between the patient HPO terms and the INFO.hpo_gene HPO_id terms">"#.as_bytes());
+    hdr.push_record(r#"##INFO=<ID=hpo_match,Number=.,Type=String,Description="patient HPO terms INFO.hpo_gene is annotated with or below">"#.as_bytes());
+
+    let mut obcf = bcfutils::get_wrtr(output, &hdr);
+    obcf.set_threads(threads.clone())
+        .expect("unable to set writer threads");
+
+    let fs: Vec<&str> = fields.split(",").collect();
+    let mut scores: HashMap<usize, (f64, Vec<usize>)> = HashMap::new();
+    let mut b = Buffer::new();
+    for record_result in bcf.records() {
+        let mut record = record_result.expect("fail to read record");
+        obcf.translate(&mut record);
+
+        let mut genes: Vec<String> = vec![];
+        for f in &fs {
+            if let Ok(Some(vals)) = record.info_shared_buffer(f.as_bytes(), &mut b).string() {
+                for v in vals.iter() {
+                    genes.push(str::from_utf8(v).unwrap().to_string());
+                }
+            }
+        }
+
+        // the best scoring gene, ties go to the first in --fields order
+        let mut best: Option<(usize, f64)> = None;
+        for g in &genes {
+            let row = match resolve_gene(g, &dtab.by_gene, model.as_ref(), &syns) {
+                Some(r) if !annotations[*r].is_empty() => *r,
+                _ => continue,
+            };
+            let score = scores
+                .entry(row)
+                .or_insert_with(|| score_gene(&mut resnik, &ont, &patient, &annotations[row]))
+                .0;
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((row, score));
+            }
+        }
+        if let Some((row, score)) = best {
+            record
+                .push_info_string(b"hpo_gene", &[dtab.genes[row].as_bytes()])
+                .expect("failed to set hpo_gene field");
+            record
+                .push_info_float(b"hpo_score", &[score as f32])
+                .expect("failed to set hpo_score field");
+            let matched = &scores[&row].1;
+            if !matched.is_empty() {
+                record
+                    .push_info_string(
+                        b"hpo_match",
+                        &matched.iter().map(|t| ont.id(*t).as_bytes()).collect::<Vec<&[u8]>>(),
+                    )
+                    .expect("failed to set hpo_match field");
+            }
+        }
+        obcf.write(&record).expect("failed to write record");
+    }
+}
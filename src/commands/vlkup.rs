@@ -0,0 +1,566 @@
+use super::glkup::get_table_arg;
+use super::glkup::table::{escape_value, parse_columns, Column, ColumnType};
+use rust_htslib::bcf::header::HeaderRecord;
+use rust_htslib::bcf::record::{Numeric, Record};
+use rust_htslib::bcf::{Header, Read};
+use rust_htslib::tbx::{self, Read as TbxRead};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process;
+use std::str;
+
+/// how the values of several source entries for one allele become one
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    First,
+    Max,
+    Concat,
+}
+
+impl Op {
+    fn parse(op: &str) -> Option<Op> {
+        return match op {
+            "first" => Some(Op::First),
+            "max" => Some(Op::Max),
+            "concat" => Some(Op::Concat),
+            _ => None,
+        };
+    }
+
+    fn as_str(&self) -> &'static str {
+        return match self {
+            Op::First => "first",
+            Op::Max => "max",
+            Op::Concat => "concat",
+        };
+    }
+}
+
+/// `op,op` one per column, or a single op for all of them
+fn parse_ops(spec: Option<&str>, columns: &Vec<Column>) -> Result<Vec<Op>, String> {
+    let ops = match spec {
+        Some(s) => s
+            .split(',')
+            .map(|o| Op::parse(o.trim()).ok_or_else(|| format!("{} is not one of first, max or concat", o)))
+            .collect::<Result<Vec<Op>, String>>()?,
+        None => vec![Op::First],
+    };
+    let ops = match ops.len() {
+        1 => vec![ops[0]; columns.len()],
+        n if n == columns.len() => ops,
+        n => return Err(format!("{} ops for {} columns", n, columns.len())),
+    };
+    for (c, op) in columns.iter().zip(&ops) {
+        let ok = match op {
+            Op::First => true,
+            Op::Max => matches!(c.col_type, ColumnType::Integer | ColumnType::Float),
+            Op::Concat => c.col_type == ColumnType::String,
+        };
+        if !ok {
+            return Err(format!("{} can't be used on the {} column {}", op.as_str(), c.col_type.as_str(), c.name));
+        }
+    }
+    return Ok(ops);
+}
+
+/// ##INFO=<ID=AF,Number=A,...> to (AF, A)
+fn info_number(line: &str) -> Option<(String, String)> {
+    let l = line.strip_prefix("##INFO=<")?;
+    let get = |key: &str| l.split(',').find_map(|kv| kv.strip_prefix(key)).map(|v| v.to_string());
+    return Some((get("ID=")?, get("Number=")?));
+}
+
+fn missing(v: &str) -> bool {
+    return v.is_empty() || v == "." || v == "NA";
+}
+
+/// trim the bases REF and ALT share, the suffix first then the prefix moving POS, so padded
+/// and split alleles compare equal. One base of each is kept
+fn normalize(pos: u64, ref_allele: &str, alt: &str) -> (u64, String, String) {
+    let (mut r, mut a) = (ref_allele.as_bytes(), alt.as_bytes());
+    while r.len() > 1 && a.len() > 1 && r[r.len() - 1] == a[a.len() - 1] {
+        r = &r[..r.len() - 1];
+        a = &a[..a.len() - 1];
+    }
+    let mut pos = pos;
+    while r.len() > 1 && a.len() > 1 && r[0] == a[0] {
+        r = &r[1..];
+        a = &a[1..];
+        pos += 1;
+    }
+    return (
+        pos,
+        String::from_utf8_lossy(r).to_ascii_uppercase(),
+        String::from_utf8_lossy(a).to_ascii_uppercase(),
+    );
+}
+
+/// the value of a source VCF INFO tag for its i-th ALT, split out when the tag is Number=A/R
+fn allele_value(c: &Column, number: &str, info: Option<&Option<&str>>, i: usize) -> Option<String> {
+    let v = match (c.col_type, info) {
+        (ColumnType::Flag, Some(_)) => Some("1"),
+        (_, Some(Some(v))) => match number {
+            "A" => v.split(',').nth(i),
+            "R" => v.split(',').nth(i + 1),
+            _ => Some(*v),
+        },
+        _ => None,
+    };
+    // a whole value keeps its commas, escaped they stay one per ALT entry
+    return v.filter(|v| !missing(v)).map(|v| match (c.col_type, number) {
+        (ColumnType::String, "A" | "R") => v.to_string(),
+        (ColumnType::String, _) => escape_value(v),
+        _ => v.to_string(),
+    });
+}
+
+/// where the column values are in a source line
+enum Layout {
+    /// INFO tags, with their Number to pick an allele's value
+    Vcf(Vec<String>),
+    /// column indexes, every allele of a line gets the same values
+    Tsv {
+        pos: usize,
+        ref_allele: usize,
+        alt: usize,
+        idxs: Vec<usize>,
+    },
+}
+
+/// the values of one allele of a source line, at its normalized 0-based position
+struct Hit {
+    pos: u64,
+    ref_allele: String,
+    alt: String,
+    values: Vec<Option<String>>,
+}
+
+/// a bgzipped, tabix indexed VCF or TSV
+struct Source {
+    path: String,
+    rdr: tbx::Reader,
+    columns: Vec<Column>,
+    ops: Vec<Op>,
+    layout: Layout,
+}
+
+impl Source {
+    /// a TSV needs a # header line with pos, ref and alt columns, in any case
+    fn open(path: &str, columns: Vec<Column>, ops: Vec<Op>) -> Result<Source, String> {
+        let rdr = tbx::Reader::from_path(path).map_err(|e| e.to_string())?;
+        let hdr = rdr.header();
+        let layout = match hdr.first().is_some_and(|l| l.starts_with("##fileformat=VCF")) {
+            true => {
+                let numbers = hdr.iter().filter_map(|l| info_number(l)).collect::<HashMap<String, String>>();
+                let numbers = columns
+                    .iter()
+                    .map(|c| {
+                        numbers
+                            .get(&c.name)
+                            .cloned()
+                            .ok_or_else(|| format!("{} has no INFO {} in its header", path, c.name))
+                    })
+                    .collect::<Result<Vec<String>, String>>()?;
+                Layout::Vcf(numbers)
+            }
+            false => {
+                let names = match hdr.last() {
+                    Some(l) => l.trim_start_matches('#').split('\t').map(|c| c.trim()).collect::<Vec<&str>>(),
+                    None => return Err(format!("{} has no # header line naming its columns", path)),
+                };
+                let find = |alts: &[&str]| -> Result<usize, String> {
+                    return names
+                        .iter()
+                        .position(|n| alts.contains(&n.to_ascii_lowercase().as_str()))
+                        .ok_or_else(|| format!("{} has no {} column", path, alts[0]));
+                };
+                Layout::Tsv {
+                    pos: find(&["pos", "position", "pos(1-based)", "start"])?,
+                    ref_allele: find(&["ref"])?,
+                    alt: find(&["alt"])?,
+                    idxs: columns
+                        .iter()
+                        .map(|c| {
+                            names
+                                .iter()
+                                .position(|n| *n == c.name)
+                                .ok_or_else(|| format!("{} has no {} column", path, c.name))
+                        })
+                        .collect::<Result<Vec<usize>, String>>()?,
+                }
+            }
+        };
+        return Ok(Source {
+            path: path.to_string(),
+            rdr,
+            columns,
+            ops,
+            layout,
+        });
+    }
+
+    /// the source lines overlapping a 0-based half-open region, split per allele. Sources naming
+    /// contigs with or without chr are both found, TSV lines are found by their pos column only
+    fn fetch(&mut self, chrom: &str, start: u64, end: u64) -> Vec<Hit> {
+        let alias = match chrom.strip_prefix("chr") {
+            Some(c) => c.to_string(),
+            None => format!("chr{}", chrom),
+        };
+        let tid = match self.rdr.tid(chrom).or_else(|_| self.rdr.tid(&alias)) {
+            Ok(t) => t,
+            Err(_) => return vec![],
+        };
+        if self.rdr.fetch(tid, start, end).is_err() {
+            return vec![];
+        }
+        let mut hits = vec![];
+        for line in self.rdr.records() {
+            let line = line.expect("failed to read source line");
+            let l = str::from_utf8(&line).expect("source line isn't utf8");
+            let cols = l.split('\t').collect::<Vec<&str>>();
+            match &self.layout {
+                Layout::Vcf(numbers) => {
+                    let pos = match cols.get(1).and_then(|p| p.parse::<u64>().ok()) {
+                        Some(p) if cols.len() >= 8 && p > 0 => p - 1,
+                        _ => continue,
+                    };
+                    let info = cols[7]
+                        .split(';')
+                        .map(|kv| match kv.split_once('=') {
+                            Some((k, v)) => (k, Some(v)),
+                            None => (kv, None),
+                        })
+                        .collect::<HashMap<&str, Option<&str>>>();
+                    for (i, alt) in cols[4].split(',').enumerate() {
+                        let values = self
+                            .columns
+                            .iter()
+                            .zip(numbers)
+                            .map(|(c, number)| allele_value(c, number, info.get(c.name.as_str()), i))
+                            .collect();
+                        let (pos, ref_allele, alt) = normalize(pos, cols[3], alt);
+                        hits.push(Hit {
+                            pos,
+                            ref_allele,
+                            alt,
+                            values,
+                        });
+                    }
+                }
+                Layout::Tsv {
+                    pos: p,
+                    ref_allele,
+                    alt,
+                    idxs,
+                } => {
+                    let pos = match cols.get(*p).and_then(|p| p.parse::<u64>().ok()) {
+                        Some(p) if p > 0 => p - 1,
+                        _ => continue,
+                    };
+                    let values = self
+                        .columns
+                        .iter()
+                        .zip(idxs)
+                        .map(|(c, i)| {
+                            cols.get(*i).filter(|v| !missing(v)).map(|v| match c.col_type {
+                                ColumnType::String => escape_value(v),
+                                _ => v.to_string(),
+                            })
+                        })
+                        .collect::<Vec<Option<String>>>();
+                    for a in cols.get(*alt).unwrap_or(&"").split(',') {
+                        let (pos, ref_allele, alt) = normalize(pos, cols.get(*ref_allele).unwrap_or(&""), a);
+                        hits.push(Hit {
+                            pos,
+                            ref_allele,
+                            alt,
+                            values: values.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        return hits;
+    }
+}
+
+/// the values of the source entries matching an allele, merged by op
+fn merge(op: Op, vals: Vec<&str>) -> Option<String> {
+    return match op {
+        Op::First => vals.first().map(|v| v.to_string()),
+        Op::Max => vals
+            .iter()
+            .filter_map(|v| v.parse::<f64>().ok().map(|f| (f, v)))
+            .fold(None, |m: Option<(f64, &&str)>, (f, v)| match m {
+                Some((mf, _)) if mf >= f => m,
+                _ => Some((f, v)),
+            })
+            .map(|(_, v)| v.to_string()),
+        Op::Concat => {
+            let mut seen = HashSet::new();
+            let vals = vals.into_iter().filter(|v| seen.insert(*v)).collect::<Vec<&str>>();
+            match vals.is_empty() {
+                true => None,
+                false => Some(vals.join("|")),
+            }
+        }
+    };
+}
+
+/// one value per ALT, missing and unparseable ones written as .
+fn add_field(c: &Column, vals: &Vec<Option<String>>, record: &mut Record) {
+    let id = c.id.as_bytes();
+    let res = match c.col_type {
+        ColumnType::Integer => {
+            let vals = vals
+                .iter()
+                .map(|v| v.as_ref().and_then(|v| v.parse::<i32>().ok()))
+                .collect::<Vec<Option<i32>>>();
+            if vals.iter().all(|v| v.is_none()) {
+                return;
+            }
+            record.push_info_integer(id, &vals.iter().map(|v| v.unwrap_or_else(i32::missing)).collect::<Vec<i32>>())
+        }
+        ColumnType::Float => {
+            let vals = vals
+                .iter()
+                .map(|v| v.as_ref().and_then(|v| v.parse::<f32>().ok()))
+                .collect::<Vec<Option<f32>>>();
+            if vals.iter().all(|v| v.is_none()) {
+                return;
+            }
+            record.push_info_float(id, &vals.iter().map(|v| v.unwrap_or_else(f32::missing)).collect::<Vec<f32>>())
+        }
+        ColumnType::String => {
+            if vals.iter().all(|v| v.is_none()) {
+                return;
+            }
+            let vals = vals.iter().map(|v| v.as_deref().unwrap_or(".")).collect::<Vec<&str>>();
+            record.push_info_string(id, &vals.iter().map(|v| v.as_bytes()).collect::<Vec<&[u8]>>())
+        }
+        ColumnType::Flag => match vals.iter().flatten().any(|v| {
+            !matches!(v.to_ascii_lowercase().as_str(), "0" | "false" | "no" | "n")
+        }) {
+            true => record.push_info_flag(id),
+            false => return,
+        },
+    };
+    res.unwrap_or_else(|_| panic!("failed to set {} field", c.id));
+}
+
+fn open_sources(
+    paths: &Vec<String>,
+    columns: &Vec<String>,
+    ops: &Vec<String>,
+    prefixes: &Vec<String>,
+) -> Vec<Source> {
+    let n = paths.len();
+    let mut ids = HashSet::new();
+    let mut sources = vec![];
+    for (i, path) in paths.iter().enumerate() {
+        let spec = match get_table_arg(columns, i, n, "columns", "source") {
+            Some(c) => c,
+            None => {
+                eprintln!("Error: --source {} needs --columns name:Type,...", path);
+                process::exit(1);
+            }
+        };
+        let prefix = get_table_arg(prefixes, i, n, "prefix", "source").unwrap_or("");
+        let cols = parse_columns(spec, prefix).unwrap_or_else(|e| {
+            eprintln!("Error: bad --columns for {}: {}", path, e);
+            process::exit(1);
+        });
+        let op = parse_ops(get_table_arg(ops, i, n, "ops", "source"), &cols).unwrap_or_else(|e| {
+            eprintln!("Error: bad --ops for {}: {}", path, e);
+            process::exit(1);
+        });
+        for c in &cols {
+            if !ids.insert(c.id.clone()) {
+                eprintln!("Error: INFO field {} comes from more than one --source, give them a --prefix", c.id);
+                process::exit(1);
+            }
+        }
+        match Source::open(path, cols, op) {
+            Ok(s) => sources.push(s),
+            Err(e) => {
+                eprintln!("Error: unable to read source {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    return sources;
+}
+
+pub fn vlkup(
+    input: Option<&str>,
+    output: Option<&str>,
+    sources: &Vec<String>,
+    columns: &Vec<String>,
+    ops: &Vec<String>,
+    prefixes: &Vec<String>,
+    threads: &usize,
+) {
+    if sources.is_empty() {
+        eprintln!("Error: need at least one --source to annotate from");
+        process::exit(1);
+    }
+    let mut sources = open_sources(sources, columns, ops, prefixes);
+
+    let mut bcf = bcfutils::get_rdr(input);
+    bcf.set_threads(threads.clone())
+        .expect("unable to set reader threads");
+    let hdrv = bcf.header();
+    let mut hdr = Header::from_template(&hdrv);
+    let input_ids = hdrv
+        .header_records()
+        .iter()
+        .filter_map(|r| match r {
+            HeaderRecord::Info { values, .. } => values.get("ID").cloned(),
+            _ => None,
+        })
+        .collect::<HashSet<String>>();
+    for s in &sources {
+        if let Some(c) = s.columns.iter().find(|c| input_ids.contains(&c.id)) {
+            eprintln!("Error: INFO field {} from --source {} is already in the input header, give it a --prefix or a name=ID", c.id, s.path);
+            process::exit(1);
+        }
+    }
+    for s in &sources {
+        let file = Path::new(&s.path)
+            .file_name()
+            .map_or(s.path.as_str(), |f| f.to_str().unwrap_or(&s.path));
+        for (c, op) in s.columns.iter().zip(&s.ops) {
+            let number = match c.col_type {
+                ColumnType::Flag => "0",
+                _ => "A",
+            };
+            hdr.push_record(format!("##INFO=<ID={},Number={},Type={},Description=\"{} from {}, {} of the entries matching each ALT\">", c.id, number, c.col_type.as_str(), c.name, file, op.as_str()).as_bytes());
+        }
+    }
+
+    let mut obcf = bcfutils::get_wrtr(output, &hdr);
+    obcf.set_threads(threads.clone())
+        .expect("unable to set writer threads");
+
+    for record_result in bcf.records() {
+        let mut record = record_result.expect("fail to read record");
+        let chrom = str::from_utf8(record.header().rid2name(record.rid().expect("record has no contig")).unwrap())
+            .unwrap()
+            .to_string();
+        let pos = record.pos() as u64;
+        let alleles = record
+            .alleles()
+            .iter()
+            .map(|a| str::from_utf8(a).unwrap().to_string())
+            .collect::<Vec<String>>();
+        // each ALT of a multiallelic record is normalized on its own
+        let queries = alleles[1..]
+            .iter()
+            .map(|alt| normalize(pos, &alleles[0], alt))
+            .collect::<Vec<(u64, String, String)>>();
+        obcf.translate(&mut record);
+
+        for s in sources.iter_mut() {
+            let hits = s.fetch(&chrom, pos, pos + alleles[0].len().max(1) as u64);
+            if hits.is_empty() {
+                continue;
+            }
+            let matches = queries
+                .iter()
+                .map(|(p, r, a)| {
+                    hits.iter()
+                        .filter(|h| h.pos == *p && h.ref_allele == *r && h.alt == *a)
+                        .collect::<Vec<&Hit>>()
+                })
+                .collect::<Vec<Vec<&Hit>>>();
+            for (i, (c, op)) in s.columns.iter().zip(&s.ops).enumerate() {
+                let vals = matches
+                    .iter()
+                    .map(|m| merge(*op, m.iter().filter_map(|h| h.values[i].as_deref()).collect()))
+                    .collect::<Vec<Option<String>>>();
+                add_field(c, &vals, &mut record);
+            }
+        }
+        obcf.write(&record).expect("failed to write record");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_split_alleles() {
+        // REF=ACT ALT=A,ACTT split per ALT, then queries written with other padding
+        let del = normalize(99, "ACT", "A");
+        let ins = normalize(99, "ACT", "ACTT");
+        assert_eq!(del, (99, "ACT".to_string(), "A".to_string()));
+        assert_eq!(ins, (100, "C".to_string(), "CT".to_string()));
+        assert_eq!(normalize(99, "ACTG", "AG"), del);
+        assert_eq!(normalize(100, "C", "CT"), ins);
+        assert_eq!(normalize(99, "ac", "act"), ins);
+        // deleting C isn't deleting CT, the trimmed ALT=AT is
+        assert_ne!(normalize(99, "AC", "A"), del);
+        assert_eq!(normalize(99, "ACT", "AT"), normalize(99, "AC", "A"));
+        // an SNV padded into an MNV
+        assert_eq!(normalize(99, "GAT", "GCT"), (100, "A".to_string(), "C".to_string()));
+    }
+
+    #[test]
+    fn merge_ops() {
+        assert_eq!(merge(Op::First, vec!["b", "a"]), Some("b".to_string()));
+        assert_eq!(merge(Op::First, vec![]), None);
+        // the max keeps the source's formatting, unparseable values are skipped
+        assert_eq!(merge(Op::Max, vec!["0.5", "x", "1e-1", "2.50"]), Some("2.50".to_string()));
+        assert_eq!(merge(Op::Max, vec!["-1", "-3"]), Some("-1".to_string()));
+        assert_eq!(merge(Op::Max, vec!["x"]), None);
+        assert_eq!(merge(Op::Concat, vec!["b", "a", "b"]), Some("b|a".to_string()));
+        assert_eq!(merge(Op::Concat, vec![]), None);
+    }
+
+    #[test]
+    fn ops_fit_the_column_types() {
+        let cols = parse_columns("AF:Float,CLNSIG,DB:Flag", "").unwrap();
+        assert_eq!(parse_ops(None, &cols), Ok(vec![Op::First; 3]));
+        assert_eq!(parse_ops(Some("first"), &cols), Ok(vec![Op::First; 3]));
+        assert_eq!(parse_ops(Some("max, concat,first"), &cols), Ok(vec![Op::Max, Op::Concat, Op::First]));
+        assert!(parse_ops(Some("max"), &cols).is_err());
+        assert!(parse_ops(Some("concat,concat,first"), &cols).is_err());
+        assert!(parse_ops(Some("first,max,first"), &cols).is_err());
+        assert!(parse_ops(Some("max,first"), &cols).is_err());
+        assert!(parse_ops(Some("sum"), &cols).is_err());
+    }
+
+    #[test]
+    fn info_numbers() {
+        assert_eq!(
+            info_number(r#"##INFO=<ID=AF,Number=A,Type=Float,Description="af, per ALT">"#),
+            Some(("AF".to_string(), "A".to_string()))
+        );
+        assert_eq!(
+            info_number(r#"##INFO=<ID=DB,Number=0,Type=Flag,Description="db">"#),
+            Some(("DB".to_string(), "0".to_string()))
+        );
+        assert_eq!(info_number(r#"##FORMAT=<ID=GT,Number=1,Type=String,Description="gt">"#), None);
+        assert_eq!(info_number(r#"##INFO=<ID=X,Type=String,Description="x">"#), None);
+    }
+
+    #[test]
+    fn allele_values() {
+        let cols = parse_columns("AF:Float,AD:Integer,SP,SR,DB:Flag", "").unwrap();
+        let v = |c: usize, number: &str, info: Option<Option<&str>>, i: usize| {
+            allele_value(&cols[c], number, info.as_ref(), i)
+        };
+        assert_eq!(v(0, "A", Some(Some("0.1,0.2")), 1), Some("0.2".to_string()));
+        assert_eq!(v(0, "A", Some(Some("0.1,.")), 1), None);
+        assert_eq!(v(0, "A", Some(Some("0.1")), 1), None);
+        assert_eq!(v(1, "R", Some(Some("10,3,4")), 0), Some("3".to_string()));
+        assert_eq!(v(1, "R", Some(Some("10,3,4")), 1), Some("4".to_string()));
+        // a per-ALT string is already one value, a whole one is escaped to stay one
+        assert_eq!(v(2, "A", Some(Some("a b,c")), 0), Some("a b".to_string()));
+        assert_eq!(v(3, ".", Some(Some("a b,c")), 1), Some("a%20b%2Cc".to_string()));
+        assert_eq!(v(3, ".", None, 0), None);
+        assert_eq!(v(4, "0", Some(None), 1), Some("1".to_string()));
+        assert_eq!(v(4, "0", None, 0), None);
+    }
+}
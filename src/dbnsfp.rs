@@ -0,0 +1,258 @@
+use crate::open_table;
+use crate::pool::StringPool;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::process;
+
+// lookups/ isn't checked in, building with --features embedded-tables compiles in a local copy
+#[cfg(feature = "embedded-tables")]
+static DBNSFP: Option<&[u8]> = Some(include_bytes!("../lookups/dbNSFP4.2_gene.semicolon_replaced.txt"));
+#[cfg(not(feature = "embedded-tables"))]
+static DBNSFP: Option<&[u8]> = None;
+
+#[derive(Debug, Deserialize)]
+struct DBrow {
+    #[serde(rename = "Gene_name")]
+    gene: String,
+    #[serde(rename = "Gene_other_names")]
+    gene_syn: String,
+    #[serde(rename = "Gene_full_name")]
+    gene_full: String,
+    #[serde(rename = "Pathway(Uniprot)")]
+    pathway_uniprot: String,
+    #[serde(rename = "Pathway(BioCarta)_full")]
+    pathway_biocarta: String,
+    #[serde(rename = "Pathway(ConsensusPathDB)")]
+    pathway_consensusPathDB: String,
+    #[serde(rename = "Pathway(KEGG)_full")]
+    pathway_kegg: String,
+    #[serde(rename = "Function_description")]
+    gene_function: String,
+    #[serde(rename = "Disease_description")]
+    gene_disease: String,
+    #[serde(rename = "MIM_phenotype_id")]
+    MIM_phenotype_id: String,
+    #[serde(rename = "MIM_disease")]
+    MIM_disease: String,
+    #[serde(rename = "Orphanet_disorder_id")]
+    orphanet_id: String,
+    #[serde(rename = "Orphanet_disorder")]
+    orphanet_disorder: String,
+    #[serde(rename = "Orphanet_association_type")]
+    orphanet_assoc_type: String,
+    #[serde(rename = "Trait_association(GWAS)")]
+    GWAS_trait: String,
+    #[serde(rename = "HPO_id")]
+    HPO_id: String,
+    #[serde(rename = "HPO_name")]
+    HPO_name: String,
+    #[serde(rename = "GO_biological_process")]
+    GO_bio_process: String,
+    #[serde(rename = "GO_cellular_component")]
+    GO_cellular_comp: String,
+    #[serde(rename = "GO_molecular_function")]
+    GO_molecular_func: String,
+    #[serde(rename = "Tissue_specificity(Uniprot)")]
+    UNIPROT_tissue_specificity: String,
+    #[serde(rename = "Expression(egenetics)")]
+    egenetics_expression: String,
+    #[serde(rename = "Expression(GNF/Atlas)")]
+    GNF_atlas_expression: String,
+    #[serde(rename = "MGI_mouse_gene")]
+    MGI_mouse_gene: String,
+    #[serde(rename = "MGI_mouse_phenotype")]
+    MGI_mouse_phenotype: String,
+}
+
+/// how a dbNSFP column is written, free text as one escaped String, lists split on the
+/// --dbnsfp-sep separator
+#[derive(Clone, Copy, PartialEq)]
+pub enum DbnsfpKind {
+    Text,
+    List,
+    IntList,
+}
+
+/// INFO field, dbNSFP gene column, kind, in the order of DBrow::values
+pub const DBNSFP_FIELDS: [(&str, &str, DbnsfpKind); 24] = [
+    ("gene_syn", "Gene_other_names", DbnsfpKind::List),
+    ("gene_full", "Gene_full_name", DbnsfpKind::Text),
+    ("pathway_uniprot", "Pathway(Uniprot)", DbnsfpKind::List),
+    ("pathway_biocarta", "Pathway(BioCarta)_full", DbnsfpKind::List),
+    ("pathway_consensusPathDB", "Pathway(ConsensusPathDB)", DbnsfpKind::List),
+    ("pathway_kegg", "Pathway(KEGG)_full", DbnsfpKind::List),
+    ("gene_function", "Function_description", DbnsfpKind::Text),
+    ("gene_disease", "Disease_description", DbnsfpKind::Text),
+    ("MIM_phenotype_id", "MIM_phenotype_id", DbnsfpKind::IntList),
+    ("MIM_disease", "MIM_disease", DbnsfpKind::List),
+    ("orphanet_id", "Orphanet_disorder_id", DbnsfpKind::IntList),
+    ("orphanet_disorder", "Orphanet_disorder", DbnsfpKind::List),
+    ("orphanet_assoc_type", "Orphanet_association_type", DbnsfpKind::List),
+    ("GWAS_trait", "Trait_association(GWAS)", DbnsfpKind::List),
+    ("HPO_id", "HPO_id", DbnsfpKind::List),
+    ("HPO_name", "HPO_name", DbnsfpKind::List),
+    ("GO_bio_process", "GO_biological_process", DbnsfpKind::List),
+    ("GO_cellular_comp", "GO_cellular_component", DbnsfpKind::List),
+    ("GO_molecular_func", "GO_molecular_function", DbnsfpKind::List),
+    ("UNIPROT_tissue_specificity", "Tissue_specificity(Uniprot)", DbnsfpKind::Text),
+    ("egenetics_expression", "Expression(egenetics)", DbnsfpKind::List),
+    ("GNF_atlas_expression", "Expression(GNF/Atlas)", DbnsfpKind::List),
+    ("MGI_mouse_gene", "MGI_mouse_gene", DbnsfpKind::Text),
+    ("MGI_mouse_phenotype", "MGI_mouse_phenotype", DbnsfpKind::List),
+];
+
+/// the items of a dbNSFP list value, . and empty ones dropped
+pub fn split_dbnsfp<'a>(val: &'a str, sep: &str) -> Vec<&'a str> {
+    return val
+        .split(sep)
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && *v != ".")
+        .collect();
+}
+
+impl DBrow {
+    fn values(&self) -> [&str; 24] {
+        return [
+            &self.gene_syn,
+            &self.gene_full,
+            &self.pathway_uniprot,
+            &self.pathway_biocarta,
+            &self.pathway_consensusPathDB,
+            &self.pathway_kegg,
+            &self.gene_function,
+            &self.gene_disease,
+            &self.MIM_phenotype_id,
+            &self.MIM_disease,
+            &self.orphanet_id,
+            &self.orphanet_disorder,
+            &self.orphanet_assoc_type,
+            &self.GWAS_trait,
+            &self.HPO_id,
+            &self.HPO_name,
+            &self.GO_bio_process,
+            &self.GO_cellular_comp,
+            &self.GO_molecular_func,
+            &self.UNIPROT_tissue_specificity,
+            &self.egenetics_expression,
+            &self.GNF_atlas_expression,
+            &self.MGI_mouse_gene,
+            &self.MGI_mouse_phenotype,
+        ];
+    }
+}
+
+/// dbNSFP gene rows, the DBNSFP_FIELDS values of each kept columnar as StringPool ids
+#[derive(Default)]
+pub struct DbnsfpTable {
+    pub genes: Vec<String>,
+    pub by_gene: HashMap<String, usize>,
+    values: Vec<u32>,
+    pool: StringPool,
+}
+
+impl DbnsfpTable {
+    fn push(&mut self, dbrow: &DBrow) {
+        self.by_gene.insert(dbrow.gene.clone(), self.genes.len());
+        self.genes.push(dbrow.gene.clone());
+        for v in dbrow.values() {
+            self.values.push(self.pool.intern(v));
+        }
+    }
+
+    /// one DBNSFP_FIELDS value of a row, by INFO field
+    pub fn get(&self, row: usize, id: &str) -> Option<&str> {
+        let i = DBNSFP_FIELDS.iter().position(|(f, _, _)| *f == id)?;
+        return Some(self.pool.get(self.values[row * DBNSFP_FIELDS.len() + i]));
+    }
+
+    pub fn values(&self, row: usize) -> Vec<&str> {
+        let n = DBNSFP_FIELDS.len();
+        return self.values[row * n..(row + 1) * n]
+            .iter()
+            .map(|id| self.pool.get(*id))
+            .collect();
+    }
+}
+
+/// --dbnsfp-table or the embedded copy
+pub fn open_dbnsfp(path: Option<&str>) -> Option<Box<dyn io::Read>> {
+    return open_table(path, DBNSFP);
+}
+
+/// one pass over the dbNSFP gene table, the synonyms are always kept and the rows only with
+/// --dbnsfp
+pub fn read_dbnsfp(table: Box<dyn io::Read>, rows: bool) -> (DbnsfpTable, HashMap<String, String>) {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .from_reader(table);
+    let headers = match rdr.headers() {
+        Ok(h) => h.clone(),
+        Err(e) => {
+            eprintln!("Error: unable to read dbNSFP table header: {}", e);
+            process::exit(1);
+        }
+    };
+    let (gene_idx, syn_idx) = match (
+        headers.iter().position(|h| h == "Gene_name"),
+        headers.iter().position(|h| h == "Gene_other_names"),
+    ) {
+        (Some(g), Some(s)) => (g, s),
+        _ => {
+            eprintln!("Error: dbNSFP table has no Gene_name or Gene_other_names column");
+            process::exit(1);
+        }
+    };
+    let mut dtab = DbnsfpTable::default();
+    let mut gene_syns = vec![];
+    for result in rdr.records() {
+        let rec = match result {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Error: unable to read dbNSFP table: {}", e);
+                process::exit(1);
+            }
+        };
+        gene_syns.push((rec[gene_idx].to_string(), rec[syn_idx].to_string()));
+        if !rows {
+            continue;
+        }
+        match rec.deserialize::<DBrow>(Some(&headers)) {
+            Ok(dbrow) => dtab.push(&dbrow),
+            Err(e) => {
+                eprintln!(
+                    "serde had serializing dbnsfp, check columns match code: {}",
+                    e
+                );
+                process::exit(1);
+            }
+        }
+    }
+    dtab.pool.freeze();
+    dtab.values.shrink_to_fit();
+    return (dtab, build_synonym_map(&gene_syns));
+}
+
+/// dbNSFP Gene_other_names to the current symbol, names shared by more than one gene are dropped
+fn build_synonym_map(gene_syns: &Vec<(String, String)>) -> HashMap<String, String> {
+    let mut syns: HashMap<String, Option<String>> = HashMap::new();
+    for (gene, gene_syn) in gene_syns.iter() {
+        for syn in gene_syn.split([';', ',', '|']) {
+            let syn = syn.trim();
+            if syn.is_empty() || syn == "." || syn == gene {
+                continue;
+            }
+            syns.entry(syn.to_string())
+                .and_modify(|g| {
+                    if g.as_deref() != Some(gene) {
+                        *g = None
+                    }
+                })
+                .or_insert_with(|| Some(gene.clone()));
+        }
+    }
+    return syns
+        .into_iter()
+        .filter_map(|(syn, g)| g.map(|g| (syn, g)))
+        .collect();
+}
@@ -0,0 +1,233 @@
+use crate::gff::{first, parse_attributes, read_gff_lines, GffFormat, Transcript};
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gene {
+    /// unversioned, ie ENSG00000186092
+    pub gene_id: String,
+    pub gene_name: String,
+    /// ie HGNC:14825
+    pub hgnc_id: Option<String>,
+}
+
+/// ENSG00000186092.7 to ENSG00000186092
+pub fn unversioned(id: &str) -> &str {
+    return id.split('.').next().unwrap_or(id);
+}
+
+impl Gene {
+    /// gene lines, GENCODE and Ensembl GTF type them gene, Ensembl GFF3 types them by biotype
+    /// (ncRNA_gene, pseudogene, ...) with ID=gene:...
+    pub fn from_gff_line(line: &str, format: GffFormat) -> Option<Gene> {
+        let cols = line.split('\t').collect::<Vec<&str>>();
+        if cols.len() < 9 || matches!(cols[2], "exon" | "CDS") {
+            return None;
+        }
+        let attrs = parse_attributes(cols[8], format);
+        let is_gene = cols[2].ends_with("gene")
+            || first(&attrs, &["ID"]).is_some_and(|id| id.starts_with("gene:"));
+        if !is_gene {
+            return None;
+        }
+        let gene_id = match first(&attrs, &["gene_id"]) {
+            Some(g) => g,
+            None => first(&attrs, &["ID"])?.strip_prefix("gene:")?,
+        };
+        // Ensembl GFF3 only has the HGNC ID in the description, [Source:HGNC Symbol;Acc:HGNC:14825]
+        let hgnc_id = first(&attrs, &["hgnc_id"])
+            .map(|h| h.to_string())
+            .or_else(|| {
+                let desc = first(&attrs, &["description"])?;
+                let acc = desc.split("Acc:").nth(1)?;
+                Some(acc.trim_end_matches(']').to_string()).filter(|a| a.starts_with("HGNC:"))
+            });
+        return Some(Gene {
+            gene_id: unversioned(gene_id).to_string(),
+            gene_name: first(&attrs, &["gene_name", "Name"]).unwrap_or("").to_string(),
+            hgnc_id,
+        });
+    }
+}
+
+/// genes from a GFF, looked up by Ensembl gene ID (versioned or not), HGNC ID, or symbol
+#[derive(Debug, Default)]
+pub struct GeneModel {
+    genes: Vec<Gene>,
+    by_id: HashMap<String, usize>,
+    by_name: HashMap<String, usize>,
+    by_hgnc: HashMap<String, usize>,
+}
+
+impl GeneModel {
+    pub fn new() -> GeneModel {
+        return GeneModel::default();
+    }
+
+    pub fn from_gff(fp: &str) -> Result<GeneModel, Box<dyn Error>> {
+        let mut model = GeneModel::new();
+        read_gff_lines(fp, |l, format| {
+            if let Some(gene) = Gene::from_gff_line(l, format) {
+                model.add(gene);
+            } else if let Some(trns) = Transcript::from_gff_line(l, format) {
+                model.add_transcript(&trns);
+            }
+        })?;
+        return Ok(model);
+    }
+
+    /// add a gene, filling in the name and HGNC ID of one already seen when they were missing
+    pub fn add(&mut self, gene: Gene) {
+        if gene.gene_id.is_empty() {
+            return;
+        }
+        let idx = match self.by_id.get(&gene.gene_id) {
+            Some(&i) => {
+                let g = &mut self.genes[i];
+                if g.gene_name.is_empty() {
+                    g.gene_name = gene.gene_name;
+                }
+                if g.hgnc_id.is_none() {
+                    g.hgnc_id = gene.hgnc_id;
+                }
+                i
+            }
+            None => {
+                self.genes.push(gene);
+                self.by_id
+                    .insert(self.genes[self.genes.len() - 1].gene_id.clone(), self.genes.len() - 1);
+                self.genes.len() - 1
+            }
+        };
+        let g = &self.genes[idx];
+        // PAR genes share a symbol, the first one seen keeps it
+        if !g.gene_name.is_empty() {
+            self.by_name.entry(g.gene_name.clone()).or_insert(idx);
+        }
+        if let Some(h) = g.hgnc_id.as_ref() {
+            self.by_hgnc.entry(h.clone()).or_insert(idx);
+        }
+    }
+
+    pub fn add_transcript(&mut self, trns: &Transcript) {
+        self.add(Gene {
+            gene_id: unversioned(&trns.gene_id).to_string(),
+            gene_name: trns.gene_name.clone(),
+            hgnc_id: trns.hgnc_id.clone(),
+        });
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Gene> {
+        let idx = match key.starts_with("HGNC:") {
+            true => self.by_hgnc.get(key),
+            false => self
+                .by_id
+                .get(unversioned(key))
+                .or_else(|| self.by_name.get(key)),
+        };
+        return idx.map(|i| &self.genes[*i]);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.genes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.genes.is_empty();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Gene> {
+        return self.genes.iter();
+    }
+}
+
+/// the row of a gene table for a gene name or ID. Ensembl gene IDs and HGNC IDs go
+/// through the gene model, outdated symbols through the dbNSFP synonyms
+pub fn resolve_gene<'a, T>(
+    gene: &str,
+    table: &'a HashMap<String, T>,
+    model: Option<&GeneModel>,
+    syns: &HashMap<String, String>,
+) -> Option<&'a T> {
+    if let Some(row) = table.get(gene) {
+        return Some(row);
+    }
+    let name = model
+        .and_then(|m| m.get(gene))
+        .map_or(gene, |g| g.gene_name.as_str());
+    if let Some(row) = table.get(name) {
+        return Some(row);
+    }
+    return syns.get(name).and_then(|s| table.get(s));
+}
+
+/// the distinct rows genes resolve to, two names for one gene give one row
+pub fn resolve_genes(
+    genes: &Vec<String>,
+    by_gene: &HashMap<String, usize>,
+    model: Option<&GeneModel>,
+    syns: &HashMap<String, String>,
+) -> Vec<usize> {
+    let mut rows = vec![];
+    for g in genes {
+        if let Some(row) = resolve_gene(g, by_gene, model, syns) {
+            if !rows.contains(row) {
+                rows.push(*row);
+            }
+        }
+    }
+    return rows;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENCODE_GENE: &str = "chr1\tHAVANA\tgene\t65419\t71585\t.\t+\t.\tID=ENSG00000186092.7;gene_id=ENSG00000186092.7;gene_type=protein_coding;gene_name=OR4F5;level=2;hgnc_id=HGNC:14825;havana_gene=OTTHUMG00000001094.4";
+
+    const ENSEMBL_GFF3_GENE: &str = "1\tensembl_havana\tgene\t65419\t71585\t.\t+\t.\tID=gene:ENSG00000186092;Name=OR4F5;biotype=protein_coding;description=olfactory receptor family 4 subfamily F member 5 [Source:HGNC Symbol%3BAcc:HGNC:14825];gene_id=ENSG00000186092;logic_name=ensembl_havana_gene_homo_sapiens;version=7";
+
+    const ENSEMBL_GFF3_NCRNA: &str = "1\thavana\tncRNA_gene\t29554\t31109\t.\t+\t.\tID=gene:ENSG00000243485;Name=MIR1302-2HG;biotype=lncRNA;description=MIR1302-2 host gene [Source:HGNC Symbol%3BAcc:HGNC:52482];gene_id=ENSG00000243485;logic_name=havana_homo_sapiens;version=5";
+
+    #[test]
+    fn parses_gene_lines() {
+        let g = Gene::from_gff_line(GENCODE_GENE, GffFormat::Gff3).unwrap();
+        assert_eq!(g.gene_id, "ENSG00000186092");
+        assert_eq!(g.gene_name, "OR4F5");
+        assert_eq!(g.hgnc_id.as_deref(), Some("HGNC:14825"));
+        assert_eq!(
+            Gene::from_gff_line(ENSEMBL_GFF3_GENE, GffFormat::Gff3),
+            Some(g)
+        );
+        let nc = Gene::from_gff_line(ENSEMBL_GFF3_NCRNA, GffFormat::Gff3).unwrap();
+        assert_eq!(nc.gene_name, "MIR1302-2HG");
+        assert_eq!(nc.hgnc_id.as_deref(), Some("HGNC:52482"));
+    }
+
+    #[test]
+    fn skips_non_gene_lines() {
+        let mrna = "1\tensembl_havana\tmRNA\t65419\t71585\t.\t+\t.\tID=transcript:ENST00000641515;Parent=gene:ENSG00000186092;Name=OR4F5-201;biotype=protein_coding;transcript_id=ENST00000641515;version=2";
+        assert_eq!(Gene::from_gff_line(mrna, GffFormat::Gff3), None);
+        // the gene ID pattern in free text doesn't make it a gene line
+        let note = "1\thavana\tlnc_RNA\t29554\t31097\t.\t+\t.\tID=transcript:ENST00000473358;Parent=gene:ENSG00000243485;Note=was ID=gene:ENSG00000243485;transcript_id=ENST00000473358";
+        assert_eq!(Gene::from_gff_line(note, GffFormat::Gff3), None);
+        let exon = "1\thavana\texon\t29554\t30039\t.\t+\t.\tParent=transcript:ENST00000473358;Name=ENSE00001947070;exon_id=ENSE00001947070";
+        assert_eq!(Gene::from_gff_line(exon, GffFormat::Gff3), None);
+    }
+
+    #[test]
+    fn looks_up_by_any_id() {
+        let mut model = GeneModel::new();
+        model.add(Gene {
+            gene_id: "ENSG00000186092".to_string(),
+            gene_name: String::new(),
+            hgnc_id: None,
+        });
+        model.add(Gene::from_gff_line(GENCODE_GENE, GffFormat::Gff3).unwrap());
+        assert_eq!(model.len(), 1);
+        assert_eq!(model.get("ENSG00000186092.7").unwrap().gene_name, "OR4F5");
+        assert_eq!(model.get("OR4F5").unwrap().gene_id, "ENSG00000186092");
+        assert_eq!(model.get("HGNC:14825").unwrap().gene_name, "OR4F5");
+        assert!(model.get("OR4F6").is_none());
+    }
+}
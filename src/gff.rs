@@ -0,0 +1,539 @@
+use rust_htslib::bgzf;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::{prelude::*, BufReader};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GffFormat {
+    Gff3,
+    Gtf,
+}
+
+/// GFF3 attributes are `key=value;`, GTF attributes are `key "value";`
+pub fn detect_gff_format(attrs: &str) -> GffFormat {
+    return match (attrs.find('='), attrs.find(' ')) {
+        (Some(eq), Some(sp)) if sp < eq => GffFormat::Gtf,
+        (Some(_), _) => GffFormat::Gff3,
+        (None, _) => GffFormat::Gtf,
+    };
+}
+
+fn percent_decode(s: &str) -> String {
+    if !s.contains('%') {
+        return s.to_string();
+    }
+    let b = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%'
+            && i + 2 < b.len()
+            && b[i + 1].is_ascii_hexdigit()
+            && b[i + 2].is_ascii_hexdigit()
+        {
+            out.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+            i += 3;
+            continue;
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    return String::from_utf8_lossy(&out).into_owned();
+}
+
+/// split column 9 into key -> values. GFF3 lists (`tag=a,b`) and repeated GTF keys
+/// (`tag "a"; tag "b";`) both end up as multiple values under one key
+pub fn parse_attributes(attrs: &str, format: GffFormat) -> HashMap<String, Vec<String>> {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for kv in attrs.split(';') {
+        let kv = kv.trim();
+        if kv.is_empty() {
+            continue;
+        }
+        match format {
+            GffFormat::Gff3 => {
+                let (k, v) = match kv.split_once('=') {
+                    Some(kv) => kv,
+                    None => continue,
+                };
+                let vals = map.entry(k.trim().to_string()).or_default();
+                for v in v.split(',') {
+                    vals.push(percent_decode(v));
+                }
+            }
+            GffFormat::Gtf => {
+                let (k, v) = match kv.split_once(' ') {
+                    Some(kv) => kv,
+                    None => continue,
+                };
+                map.entry(k.to_string())
+                    .or_default()
+                    .push(v.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    return map;
+}
+
+/// GENCODE repeats the PAR transcripts on chrY, with this after the version
+const PAR_Y: &str = "_PAR_Y";
+
+/// a transcript ID without its version, the chrY copy of a PAR transcript keeps its suffix,
+/// ie ENST00000381192.5_PAR_Y to ENST00000381192_PAR_Y
+pub fn transcript_key(id: &str) -> String {
+    let (id, par) = match id.strip_suffix(PAR_Y) {
+        Some(id) => (id, PAR_Y),
+        None => (id, ""),
+    };
+    return format!("{}{}", id.split('.').next().unwrap_or(id), par);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transcript {
+    pub gene_id: String,
+    pub gene_name: String,
+    /// unversioned, ie ENST00000641515 or ENST00000381192_PAR_Y
+    pub transcript_id: String,
+    /// the part after the '.' of a versioned ID, ie 2
+    pub version: Option<String>,
+    pub biotype: String,
+    pub tags: HashSet<String>,
+    pub tsl: Option<u8>,
+    pub level: Option<u8>,
+    /// RefSeq match from Dbxref/db_xref, present in MANE releases
+    pub refseq: Option<String>,
+    /// '+' or '-'
+    pub strand: char,
+    /// GENCODE only, ie HGNC:14825
+    pub hgnc_id: Option<String>,
+}
+
+pub(crate) fn first<'a>(attrs: &'a HashMap<String, Vec<String>>, keys: &[&str]) -> Option<&'a str> {
+    for k in keys {
+        if let Some(v) = attrs.get(*k).and_then(|v| v.first()) {
+            return Some(v);
+        }
+    }
+    return None;
+}
+
+/// TSL is `1`..`5`, `NA`, or in Ensembl GTF `1 (assigned to previous version 2)`
+fn parse_tsl(tsl: &str) -> Option<u8> {
+    return tsl.split_whitespace().next().and_then(|t| t.parse().ok());
+}
+
+impl Transcript {
+    /// parse one GFF3 or GTF line, returns None for anything that is not a transcript
+    pub fn from_gff_line(line: &str, format: GffFormat) -> Option<Transcript> {
+        let cols = line.split('\t').collect::<Vec<&str>>();
+        if cols.len() < 9 {
+            return None;
+        }
+        // the bulk of the file, not worth parsing the attributes of
+        if matches!(cols[2], "exon" | "CDS") {
+            return None;
+        }
+        // Ensembl GFF3 types transcripts by biotype (mRNA, lnc_RNA, ...) with ID=transcript:...
+        let attrs = parse_attributes(cols[8], format);
+        let is_trns = cols[2] == "transcript"
+            || first(&attrs, &["ID"]).is_some_and(|id| id.starts_with("transcript:"));
+        if !is_trns {
+            return None;
+        }
+
+        let trns_id = first(&attrs, &["transcript_id"])?;
+        let (transcript_id, version) = match trns_id.trim_end_matches(PAR_Y).split_once('.') {
+            Some((_, v)) => (transcript_key(trns_id), Some(v.to_string())),
+            None => (
+                trns_id.to_string(),
+                first(&attrs, &["transcript_version", "version"]).map(|v| v.to_string()),
+            ),
+        };
+
+        let gene_id = match first(&attrs, &["gene_id"]) {
+            Some(g) => g.to_string(),
+            None => first(&attrs, &["Parent"])
+                .map(|p| p.trim_start_matches("gene:").to_string())
+                .unwrap_or_default(),
+        };
+
+        let refseq = attrs
+            .get("Dbxref")
+            .or(attrs.get("db_xref"))
+            .and_then(|x| x.iter().find_map(|x| x.strip_prefix("RefSeq:")))
+            .map(|r| r.to_string());
+
+        return Some(Transcript {
+            gene_id,
+            gene_name: first(&attrs, &["gene_name"]).unwrap_or("").to_string(),
+            transcript_id,
+            version,
+            biotype: first(&attrs, &["transcript_type", "transcript_biotype", "biotype"])
+                .unwrap_or("")
+                .to_string(),
+            tags: attrs
+                .get("tag")
+                .map(|t| t.iter().cloned().collect())
+                .unwrap_or_default(),
+            tsl: first(&attrs, &["transcript_support_level"]).and_then(parse_tsl),
+            level: first(&attrs, &["level"]).and_then(|l| l.parse().ok()),
+            refseq,
+            strand: cols[6].chars().next().unwrap_or('.'),
+            hgnc_id: first(&attrs, &["hgnc_id"]).map(|h| h.to_string()),
+        });
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        return self.tags.contains(tag);
+    }
+
+    /// transcript ID with version, ie ENST00000641515.2 or ENST00000381192.5_PAR_Y
+    pub fn versioned_id(&self) -> String {
+        let (id, par) = match self.transcript_id.strip_suffix(PAR_Y) {
+            Some(id) => (id, PAR_Y),
+            None => (self.transcript_id.as_str(), ""),
+        };
+        return match &self.version {
+            Some(v) => format!("{}.{}{}", id, v, par),
+            None => self.transcript_id.clone(),
+        };
+    }
+
+    /// appris tag without the `appris_` prefix, ie principal_1
+    pub fn appris(&self) -> Option<&str> {
+        return self.tags.iter().find_map(|t| t.strip_prefix("appris_"));
+    }
+
+    pub fn mane(&self) -> Option<&str> {
+        if self.has_tag("MANE_Select") {
+            return Some("MANE_Select");
+        }
+        if self.has_tag("MANE_Plus_Clinical") {
+            return Some("MANE_Plus_Clinical");
+        }
+        return None;
+    }
+
+    /// cds/mRNA start/end not found tags, joined with '&'
+    pub fn uncertain_start_end(&self) -> String {
+        let possible_tags = ["cds_start_NF", "cds_end_NF", "mRNA_start_NF", "mRNA_end_NF"];
+        return possible_tags
+            .iter()
+            .filter(|t| self.has_tag(t))
+            .cloned()
+            .collect::<Vec<&str>>()
+            .join("&");
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubFeature {
+    Exon,
+    Cds,
+}
+
+/// parse an exon or CDS line, returns the transcript_key of the transcript it belongs to with its
+/// 1-based inclusive coordinates
+pub fn parse_sub_feature(line: &str, format: GffFormat) -> Option<(String, SubFeature, u64, u64)> {
+    let cols = line.split('\t').collect::<Vec<&str>>();
+    if cols.len() < 9 {
+        return None;
+    }
+    let kind = match cols[2] {
+        "exon" => SubFeature::Exon,
+        "CDS" => SubFeature::Cds,
+        _ => return None,
+    };
+    let attrs = parse_attributes(cols[8], format);
+    // Ensembl GFF3 exons and CDS only point to their transcript through Parent
+    let trns_id = match first(&attrs, &["transcript_id"]) {
+        Some(t) => t,
+        None => first(&attrs, &["Parent"])?.strip_prefix("transcript:")?,
+    };
+    return Some((transcript_key(trns_id), kind, cols[3].parse().ok()?, cols[4].parse().ok()?));
+}
+
+/// call f with every feature line of a GFF3 or GTF file, plain, gzip, or bgzip compressed, and
+/// the attribute format detected from the first one
+pub fn read_gff_lines<F: FnMut(&str, GffFormat)>(fp: &str, mut f: F) -> Result<(), Box<dyn Error>> {
+    let rdr = BufReader::new(bgzf::Reader::from_path(fp)?);
+    let mut format = None;
+    for line in rdr.lines() {
+        let l = line?;
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let attrs = match l.rsplit_once('\t') {
+            Some((_, attrs)) => attrs,
+            None => continue,
+        };
+        let format = *format.get_or_insert_with(|| detect_gff_format(attrs));
+        f(&l, format);
+    }
+    return Ok(());
+}
+
+/// exon and CDS coordinates of a transcript, 1-based inclusive and sorted by start
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrxStructure {
+    pub strand: char,
+    pub exons: Vec<(u64, u64)>,
+    pub cds: Vec<(u64, u64)>,
+}
+
+fn encode_regions(regions: &Vec<(u64, u64)>) -> String {
+    return regions
+        .iter()
+        .map(|(s, e)| format!("{}-{}", s, e))
+        .collect::<Vec<String>>()
+        .join(",");
+}
+
+fn decode_regions(s: &str) -> Option<Vec<(u64, u64)>> {
+    if s.is_empty() {
+        return Some(vec![]);
+    }
+    return s
+        .split(',')
+        .map(|r| {
+            let (s, e) = r.split_once('-')?;
+            Some((s.parse().ok()?, e.parse().ok()?))
+        })
+        .collect();
+}
+
+impl TrxStructure {
+    pub fn sort(&mut self) {
+        self.exons.sort();
+        self.cds.sort();
+    }
+
+    /// compact text form, ie `+;100-200,300-400;150-200,300-350`
+    pub fn encode(&self) -> String {
+        return format!(
+            "{};{};{}",
+            self.strand,
+            encode_regions(&self.exons),
+            encode_regions(&self.cds)
+        );
+    }
+
+    pub fn decode(s: &str) -> Option<TrxStructure> {
+        let mut parts = s.split(';');
+        return Some(TrxStructure {
+            strand: parts.next()?.chars().next()?,
+            exons: decode_regions(parts.next()?)?,
+            cds: decode_regions(parts.next()?)?,
+        });
+    }
+
+    /// introns between consecutive exons, in genomic order
+    pub fn introns(&self) -> Vec<(u64, u64)> {
+        return self
+            .exons
+            .windows(2)
+            .map(|w| (w[0].1 + 1, w[1].0 - 1))
+            .collect();
+    }
+
+    /// 1-based position of a genomic position in the CDS, read in transcript orientation,
+    /// and the CDS length. None when the position is not coding
+    pub fn cds_pos(&self, pos: u64) -> Option<(u64, u64)> {
+        let len = self.cds.iter().map(|(s, e)| e - s + 1).sum();
+        let mut before = 0;
+        for (s, e) in self.cds.iter() {
+            if pos >= *s && pos <= *e {
+                let c = before + pos - s + 1;
+                return match self.strand {
+                    '-' => Some((len - c + 1, len)),
+                    _ => Some((c, len)),
+                };
+            }
+            before += e - s + 1;
+        }
+        return None;
+    }
+
+    /// 1-based position of a genomic position in the spliced transcript, and the index of
+    /// the exon it falls in, both in transcript orientation. None when the position is not exonic
+    pub fn exon_pos(&self, pos: u64) -> Option<(u64, usize)> {
+        let mut before = 0;
+        for (i, (s, e)) in self.ordered_exons().enumerate() {
+            if pos >= *s && pos <= *e {
+                let off = match self.strand {
+                    '-' => e - pos,
+                    _ => pos - s,
+                };
+                return Some((before + off + 1, i));
+            }
+            before += e - s + 1;
+        }
+        return None;
+    }
+
+    /// transcript position of the last base before the last exon-exon junction, None for
+    /// single exon transcripts
+    pub fn last_junction(&self) -> Option<u64> {
+        if self.exons.len() < 2 {
+            return None;
+        }
+        return Some(
+            self.ordered_exons()
+                .take(self.exons.len() - 1)
+                .map(|(s, e)| e - s + 1)
+                .sum(),
+        );
+    }
+
+    fn ordered_exons(&self) -> Box<dyn Iterator<Item = &(u64, u64)> + '_> {
+        return match self.strand {
+            '-' => Box::new(self.exons.iter().rev()),
+            _ => Box::new(self.exons.iter()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENCODE_GFF3: &str = "chr1\tHAVANA\ttranscript\t65419\t71585\t.\t+\t.\tID=ENST00000641515.2;Parent=ENSG00000186092.7;gene_id=ENSG00000186092.7;transcript_id=ENST00000641515.2;gene_type=protein_coding;gene_name=OR4F5;transcript_type=protein_coding;transcript_name=OR4F5-201;level=2;protein_id=ENSP00000493376.2;transcript_support_level=NA;hgnc_id=HGNC:14825;tag=RNA_Seq_supported_partial,basic,Ensembl_canonical,MANE_Select,appris_principal_1,CCDS;ccdsid=CCDS30547.2;havana_gene=OTTHUMG00000001094.4;havana_transcript=OTTHUMT00000003223.4";
+
+    const GENCODE_GTF: &str = "chr1\tHAVANA\ttranscript\t65419\t71585\t.\t+\t.\tgene_id \"ENSG00000186092.7\"; transcript_id \"ENST00000641515.2\"; gene_type \"protein_coding\"; gene_name \"OR4F5\"; transcript_type \"protein_coding\"; transcript_name \"OR4F5-201\"; level 2; protein_id \"ENSP00000493376.2\"; transcript_support_level \"NA\"; hgnc_id \"HGNC:14825\"; tag \"RNA_Seq_supported_partial\"; tag \"basic\"; tag \"Ensembl_canonical\"; tag \"MANE_Select\"; tag \"appris_principal_1\"; tag \"CCDS\"; ccdsid \"CCDS30547.2\"; havana_gene \"OTTHUMG00000001094.4\"; havana_transcript \"OTTHUMT00000003223.4\";";
+
+    // readthrough, no CCDS tag but a ccdsid-like key, and cds_start_NF
+    const GENCODE_READTHROUGH: &str = "chr1\tHAVANA\ttranscript\t1020120\t1056118\t.\t+\t.\tID=ENST00000620552.4;Parent=ENSG00000217801.11;gene_id=ENSG00000217801.11;transcript_id=ENST00000620552.4;gene_type=protein_coding;gene_name=ENSG00000217801;transcript_type=protein_coding;transcript_name=ENST00000620552;level=2;protein_id=ENSP00000484256.1;transcript_support_level=5;tag=readthrough_transcript,mRNA_start_NF,cds_start_NF,basic;havana_transcript=OTTHUMT00000097862.2";
+
+    const ENSEMBL_GTF: &str = "1\tensembl_havana\ttranscript\t65419\t71585\t.\t+\t.\tgene_id \"ENSG00000186092\"; gene_version \"7\"; transcript_id \"ENST00000641515\"; transcript_version \"2\"; gene_name \"OR4F5\"; gene_source \"ensembl_havana\"; gene_biotype \"protein_coding\"; transcript_name \"OR4F5-201\"; transcript_source \"ensembl_havana\"; transcript_biotype \"protein_coding\"; tag \"CCDS\"; ccds_id \"CCDS30547\"; tag \"basic\"; tag \"Ensembl_canonical\"; tag \"MANE_Select\"; transcript_support_level \"1 (assigned to previous version 1)\";";
+
+    const GENCODE_EXON: &str = "chr1\tHAVANA\texon\t65419\t65433\t.\t+\t.\tID=exon:ENST00000641515.2:1;Parent=ENST00000641515.2;gene_id=ENSG00000186092.7;transcript_id=ENST00000641515.2;gene_type=protein_coding;gene_name=OR4F5;transcript_type=protein_coding;exon_number=1;exon_id=ENSE00003812156.1;level=2";
+
+    #[test]
+    fn detects_format() {
+        let attrs = |l: &'static str| l.rsplit('\t').next().unwrap();
+        assert_eq!(detect_gff_format(attrs(GENCODE_GFF3)), GffFormat::Gff3);
+        assert_eq!(detect_gff_format(attrs(GENCODE_GTF)), GffFormat::Gtf);
+        assert_eq!(detect_gff_format(attrs(ENSEMBL_GTF)), GffFormat::Gtf);
+    }
+
+    #[test]
+    fn parses_gencode_gff3() {
+        let t = Transcript::from_gff_line(GENCODE_GFF3, GffFormat::Gff3).unwrap();
+        assert_eq!(t.gene_id, "ENSG00000186092.7");
+        assert_eq!(t.gene_name, "OR4F5");
+        assert_eq!(t.transcript_id, "ENST00000641515");
+        assert_eq!(t.version.as_deref(), Some("2"));
+        assert_eq!(t.versioned_id(), "ENST00000641515.2");
+        assert_eq!(t.biotype, "protein_coding");
+        assert_eq!(t.tsl, None);
+        assert_eq!(t.level, Some(2));
+        assert_eq!(t.hgnc_id.as_deref(), Some("HGNC:14825"));
+        assert!(t.has_tag("Ensembl_canonical"));
+        assert!(t.has_tag("CCDS"));
+        assert_eq!(t.appris(), Some("principal_1"));
+        assert_eq!(t.mane(), Some("MANE_Select"));
+        assert_eq!(t.uncertain_start_end(), "");
+    }
+
+    #[test]
+    fn gtf_and_gff3_agree() {
+        let gff3 = Transcript::from_gff_line(GENCODE_GFF3, GffFormat::Gff3).unwrap();
+        let gtf = Transcript::from_gff_line(GENCODE_GTF, GffFormat::Gtf).unwrap();
+        assert_eq!(gff3, gtf);
+    }
+
+    #[test]
+    fn tags_match_whole_values() {
+        let t = Transcript::from_gff_line(GENCODE_READTHROUGH, GffFormat::Gff3).unwrap();
+        assert!(t.has_tag("readthrough_transcript"));
+        assert!(!t.has_tag("Ensembl_canonical"));
+        assert!(!t.has_tag("CCDS"));
+        assert!(!t.has_tag("basic_"));
+        assert_eq!(t.mane(), None);
+        assert_eq!(t.tsl, Some(5));
+        assert_eq!(t.uncertain_start_end(), "cds_start_NF&mRNA_start_NF");
+    }
+
+    #[test]
+    fn ensembl_gtf_version_and_tsl() {
+        let t = Transcript::from_gff_line(ENSEMBL_GTF, GffFormat::Gtf).unwrap();
+        assert_eq!(t.gene_id, "ENSG00000186092");
+        assert_eq!(t.transcript_id, "ENST00000641515");
+        assert_eq!(t.versioned_id(), "ENST00000641515.2");
+        assert_eq!(t.tsl, Some(1));
+        assert!(t.has_tag("Ensembl_canonical"));
+    }
+
+    #[test]
+    fn ensembl_gff3_transcript_by_id() {
+        let mrna = "1\tensembl_havana\tmRNA\t65419\t71585\t.\t+\t.\tID=transcript:ENST00000641515;Parent=gene:ENSG00000186092;Name=OR4F5-201;biotype=protein_coding;tag=basic;transcript_id=ENST00000641515;version=2";
+        let t = Transcript::from_gff_line(mrna, GffFormat::Gff3).unwrap();
+        assert_eq!(t.gene_id, "ENSG00000186092");
+        assert_eq!(t.versioned_id(), "ENST00000641515.2");
+        // points at a transcript but isn't one
+        let utr = "1\tensembl_havana\tfive_prime_UTR\t65419\t65563\t.\t+\t.\tParent=transcript:ENST00000641515";
+        assert_eq!(Transcript::from_gff_line(utr, GffFormat::Gff3), None);
+    }
+
+    #[test]
+    fn par_y_transcripts_keep_their_own_key() {
+        let trns = GENCODE_GFF3.replace("chr1", "chrY").replace("ENST00000641515.2", "ENST00000641515.2_PAR_Y");
+        let t = Transcript::from_gff_line(&trns, GffFormat::Gff3).unwrap();
+        assert_eq!(t.transcript_id, "ENST00000641515_PAR_Y");
+        assert_eq!(t.version.as_deref(), Some("2"));
+        assert_eq!(t.versioned_id(), "ENST00000641515.2_PAR_Y");
+        let exon = GENCODE_EXON.replace("chr1", "chrY").replace("ENST00000641515.2", "ENST00000641515.2_PAR_Y");
+        assert_eq!(
+            parse_sub_feature(&exon, GffFormat::Gff3),
+            Some(("ENST00000641515_PAR_Y".to_string(), SubFeature::Exon, 65419, 65433))
+        );
+        assert_eq!(transcript_key("ENST00000641515.2"), "ENST00000641515");
+        assert_eq!(transcript_key("ENST00000641515"), "ENST00000641515");
+    }
+
+    #[test]
+    fn unversioned_id_does_not_panic() {
+        let line = GENCODE_GFF3.replace("transcript_id=ENST00000641515.2", "transcript_id=ENST00000641515");
+        let t = Transcript::from_gff_line(&line, GffFormat::Gff3).unwrap();
+        assert_eq!(t.transcript_id, "ENST00000641515");
+        assert_eq!(t.version, None);
+    }
+
+    #[test]
+    fn parses_exon_and_cds_lines() {
+        assert_eq!(
+            parse_sub_feature(GENCODE_EXON, GffFormat::Gff3),
+            Some(("ENST00000641515".to_string(), SubFeature::Exon, 65419, 65433))
+        );
+        let ensembl = "1\tensembl_havana\tCDS\t69091\t70005\t.\t+\t0\tID=CDS:ENSP00000493376;Parent=transcript:ENST00000641515;protein_id=ENSP00000493376";
+        assert_eq!(
+            parse_sub_feature(ensembl, GffFormat::Gff3),
+            Some(("ENST00000641515".to_string(), SubFeature::Cds, 69091, 70005))
+        );
+    }
+
+    #[test]
+    fn structure_round_trip_and_cds_pos() {
+        let st = TrxStructure {
+            strand: '-',
+            exons: vec![(100, 200), (300, 400)],
+            cds: vec![(150, 200), (300, 349)],
+        };
+        assert_eq!(TrxStructure::decode(&st.encode()), Some(st.clone()));
+        assert_eq!(st.introns(), vec![(201, 299)]);
+        // minus strand, so the CDS starts at 349
+        assert_eq!(st.cds_pos(349), Some((1, 101)));
+        assert_eq!(st.cds_pos(150), Some((101, 101)));
+        assert_eq!(st.cds_pos(250), None);
+        // minus strand, so the first exon in transcript order is 300-400
+        assert_eq!(st.exon_pos(400), Some((1, 0)));
+        assert_eq!(st.exon_pos(200), Some((102, 1)));
+        assert_eq!(st.exon_pos(250), None);
+        assert_eq!(st.last_junction(), Some(101));
+    }
+
+    #[test]
+    fn skips_non_transcripts() {
+        assert_eq!(Transcript::from_gff_line(GENCODE_EXON, GffFormat::Gff3), None);
+    }
+}
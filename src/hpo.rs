@@ -0,0 +1,273 @@
+use rust_htslib::bgzf;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::{prelude::*, BufReader};
+
+/// the is_a graph of an OBO ontology, obsolete terms dropped and alt_ids pointing at the
+/// term that replaced them
+#[derive(Debug, Default)]
+pub struct Ontology {
+    terms: Vec<String>,
+    names: Vec<String>,
+    parents: Vec<Vec<usize>>,
+    ids: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+struct Stanza {
+    id: String,
+    name: String,
+    alt_ids: Vec<String>,
+    is_a: Vec<String>,
+    obsolete: bool,
+}
+
+impl Ontology {
+    pub fn from_obo(fp: &str) -> Result<Ontology, Box<dyn Error>> {
+        return Ontology::from_reader(BufReader::new(bgzf::Reader::from_path(fp)?));
+    }
+
+    pub fn from_reader<R: BufRead>(rdr: R) -> Result<Ontology, Box<dyn Error>> {
+        let mut stanzas = vec![];
+        let mut cur: Option<Stanza> = None;
+        for line in rdr.lines() {
+            let l = line?;
+            let l = l.trim();
+            if l.starts_with('[') {
+                stanzas.extend(cur.take());
+                if l == "[Term]" {
+                    cur = Some(Stanza::default());
+                }
+                continue;
+            }
+            let s = match cur.as_mut() {
+                Some(s) => s,
+                None => continue,
+            };
+            let (key, val) = match l.split_once(": ") {
+                Some(kv) => kv,
+                None => continue,
+            };
+            // is_a: HP:0000118 ! Phenotypic abnormality
+            let val = val.split(" ! ").next().unwrap_or(val).trim();
+            match key {
+                "id" => s.id = val.to_string(),
+                "name" => s.name = val.to_string(),
+                "alt_id" => s.alt_ids.push(val.to_string()),
+                "is_a" => s.is_a.push(val.to_string()),
+                "is_obsolete" => s.obsolete = val == "true",
+                _ => {}
+            }
+        }
+        stanzas.extend(cur.take());
+
+        let mut ont = Ontology::default();
+        let stanzas = stanzas
+            .into_iter()
+            .filter(|s| !s.obsolete && !s.id.is_empty())
+            .collect::<Vec<Stanza>>();
+        for s in &stanzas {
+            ont.ids.insert(s.id.clone(), ont.terms.len());
+            ont.terms.push(s.id.clone());
+            ont.names.push(s.name.clone());
+        }
+        for s in &stanzas {
+            for alt in &s.alt_ids {
+                let idx = ont.ids[&s.id];
+                ont.ids.entry(alt.clone()).or_insert(idx);
+            }
+            ont.parents.push(s.is_a.iter().filter_map(|p| ont.ids.get(p).copied()).collect());
+        }
+        return Ok(ont);
+    }
+
+    pub fn get(&self, id: &str) -> Option<usize> {
+        return self.ids.get(id).copied();
+    }
+
+    pub fn id(&self, term: usize) -> &str {
+        return &self.terms[term];
+    }
+
+    pub fn name(&self, term: usize) -> &str {
+        return &self.names[term];
+    }
+
+    pub fn len(&self) -> usize {
+        return self.terms.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.terms.is_empty();
+    }
+
+    /// the term and everything above it
+    pub fn ancestors(&self, term: usize) -> HashSet<usize> {
+        let mut anc = HashSet::new();
+        let mut stack = vec![term];
+        while let Some(t) = stack.pop() {
+            if anc.insert(t) {
+                stack.extend(self.parents[t].iter().copied());
+            }
+        }
+        return anc;
+    }
+}
+
+/// Resnik similarity over an ontology, the information content of a term is -ln of the
+/// fraction of annotated genes carrying it or a term below it
+pub struct Resnik<'a> {
+    ont: &'a Ontology,
+    ic: Vec<f64>,
+    ancestors: HashMap<usize, HashSet<usize>>,
+}
+
+impl<'a> Resnik<'a> {
+    pub fn new(ont: &'a Ontology, annotations: &Vec<Vec<usize>>) -> Resnik<'a> {
+        let mut counts = vec![0usize; ont.len()];
+        let mut annotated = 0;
+        for terms in annotations {
+            let closure = terms
+                .iter()
+                .flat_map(|t| ont.ancestors(*t))
+                .collect::<HashSet<usize>>();
+            if closure.is_empty() {
+                continue;
+            }
+            annotated += 1;
+            for t in closure {
+                counts[t] += 1;
+            }
+        }
+        let ic = counts
+            .iter()
+            .map(|c| match *c {
+                0 => 0.0,
+                c => -(c as f64 / annotated as f64).ln(),
+            })
+            .collect();
+        return Resnik {
+            ont,
+            ic,
+            ancestors: HashMap::new(),
+        };
+    }
+
+    pub fn ic(&self, term: usize) -> f64 {
+        return self.ic[term];
+    }
+
+    fn cache_ancestors(&mut self, term: usize) {
+        if !self.ancestors.contains_key(&term) {
+            self.ancestors.insert(term, self.ont.ancestors(term));
+        }
+    }
+
+    /// IC of the most informative common ancestor
+    pub fn similarity(&mut self, a: usize, b: usize) -> f64 {
+        self.cache_ancestors(a);
+        self.cache_ancestors(b);
+        let anc_b = &self.ancestors[&b];
+        return self.ancestors[&a]
+            .iter()
+            .filter(|t| anc_b.contains(t))
+            .map(|t| self.ic[*t])
+            .fold(0.0, f64::max);
+    }
+
+    /// symmetric best-match average, the mean best match of each side averaged
+    pub fn best_match_average(&mut self, a: &Vec<usize>, b: &Vec<usize>) -> f64 {
+        if a.is_empty() || b.is_empty() {
+            return 0.0;
+        }
+        let sims = a
+            .iter()
+            .map(|x| b.iter().map(|y| self.similarity(*x, *y)).collect::<Vec<f64>>())
+            .collect::<Vec<Vec<f64>>>();
+        let a_best = sims.iter().map(|r| r.iter().copied().fold(0.0, f64::max)).sum::<f64>();
+        let b_best = (0..b.len())
+            .map(|j| sims.iter().map(|r| r[j]).fold(0.0, f64::max))
+            .sum::<f64>();
+        return (a_best / a.len() as f64 + b_best / b.len() as f64) / 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // root, two branches, and a leaf under the first
+    const OBO: &str = "format-version: 1.2
+ontology: hp
+
+[Term]
+id: HP:0000001
+name: All
+
+[Term]
+id: HP:0000118
+name: Phenotypic abnormality
+is_a: HP:0000001 ! All
+
+[Term]
+id: HP:0001250
+name: Seizure
+alt_id: HP:0002279
+is_a: HP:0000118 ! Phenotypic abnormality
+
+[Term]
+id: HP:0002373
+name: Febrile seizure
+is_a: HP:0001250 ! Seizure
+
+[Term]
+id: HP:0000707
+name: Abnormality of the nervous system
+is_a: HP:0000118 ! Phenotypic abnormality
+
+[Term]
+id: HP:0000003
+name: obsolete term
+is_obsolete: true
+
+[Typedef]
+id: part_of
+name: part of
+";
+
+    #[test]
+    fn parses_obo() {
+        let ont = Ontology::from_reader(OBO.as_bytes()).unwrap();
+        assert_eq!(ont.len(), 5);
+        assert!(ont.get("HP:0000003").is_none());
+        assert!(ont.get("part_of").is_none());
+        let seizure = ont.get("HP:0001250").unwrap();
+        assert_eq!(ont.get("HP:0002279"), Some(seizure));
+        assert_eq!(ont.name(seizure), "Seizure");
+        let febrile = ont.get("HP:0002373").unwrap();
+        let anc = ont.ancestors(febrile);
+        assert_eq!(anc.len(), 4);
+        assert!(anc.contains(&seizure));
+        assert!(!anc.contains(&ont.get("HP:0000707").unwrap()));
+    }
+
+    #[test]
+    fn scores_resnik() {
+        let ont = Ontology::from_reader(OBO.as_bytes()).unwrap();
+        let t = |id: &str| ont.get(id).unwrap();
+        let annotations = vec![
+            vec![t("HP:0002373")],
+            vec![t("HP:0001250")],
+            vec![t("HP:0000707")],
+            vec![t("HP:0000707")],
+        ];
+        let mut r = Resnik::new(&ont, &annotations);
+        assert_eq!(r.ic(t("HP:0000001")), 0.0);
+        assert!((r.ic(t("HP:0001250")) - 2f64.ln()).abs() < 1e-9);
+        assert!((r.similarity(t("HP:0002373"), t("HP:0001250")) - 2f64.ln()).abs() < 1e-9);
+        assert_eq!(r.similarity(t("HP:0002373"), t("HP:0000707")), 0.0);
+        let seizure_gene = r.best_match_average(&vec![t("HP:0002373")], &vec![t("HP:0002373")]);
+        let nerve_gene = r.best_match_average(&vec![t("HP:0002373")], &vec![t("HP:0000707")]);
+        assert!(seizure_gene > nerve_gene);
+    }
+}
@@ -1,5 +1,17 @@
 extern crate rust_htslib;
 use crate::rust_htslib::bcf::{Reader, Format, Writer};
+use rust_htslib::bgzf;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+
+pub mod dbnsfp;
+pub mod genes;
+pub mod gff;
+pub mod hpo;
+pub mod pool;
 
 pub fn get_rdr(input: Option<&str>) -> rust_htslib::bcf::Reader {
     match input {
@@ -29,4 +41,31 @@ pub fn get_wrtr(input: Option<&str>, hdr: &rust_htslib::bcf::Header) -> rust_hts
     }
 }
 
+/// a comma separated list, or a file with one entry per line
+pub fn read_list(arg: Option<&str>) -> Option<HashSet<String>> {
+    let arg = arg?;
+    if Path::new(arg).is_file() {
+        let list = fs::read_to_string(arg).expect("unable to read list file");
+        return Some(
+            list.lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .collect(),
+        );
+    }
+    return Some(arg.split(',').map(|a| a.trim().to_string()).collect());
+}
 
+/// a gene table from a path, plain or gzipped, falling back to the embedded copy
+pub fn open_table(path: Option<&str>, embedded: Option<&'static [u8]>) -> Option<Box<dyn io::Read>> {
+    return match path {
+        Some(p) => match bgzf::Reader::from_path(p) {
+            Ok(r) => Some(Box::new(r)),
+            Err(e) => {
+                eprintln!("Error: unable to open gene table {}: {}", p, e);
+                process::exit(1);
+            }
+        },
+        None => embedded.map(|e| Box::new(e) as Box<dyn io::Read>),
+    };
+}
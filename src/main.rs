@@ -23,12 +23,35 @@ enum Commands {
         #[clap(long, short)]
         pedigree: Option<String>,
     },
+    #[clap(args_conflicts_with_subcommands = true)]
     MCSQ {
+        #[clap(subcommand)]
+        command: Option<McsqCommands>,
         input: Option<String>,
         #[clap(long, short)]
         output: Option<String>,
         #[clap(long, short)]
         gff: Option<String>,
+        #[clap(long)]
+        trx_index: Option<String>,
+        #[clap(long, takes_value = false, conflicts_with = "per-gene")]
+        per_allele: bool,
+        #[clap(long, takes_value = false)]
+        per_gene: bool,
+        #[clap(long, takes_value = false)]
+        emit_ranks: bool,
+        #[clap(long)]
+        explain: Option<String>,
+        #[clap(long, takes_value = false)]
+        lof: bool,
+        #[clap(long, takes_value = false)]
+        nmd: bool,
+        #[clap(long, takes_value = false)]
+        hgvs: bool,
+        #[clap(long)]
+        fasta: Option<String>,
+        #[clap(long, takes_value = false)]
+        validate: bool,
         #[clap(long, value_parser, default_value_t = 1)]
         threads: usize,
     },
@@ -37,19 +60,110 @@ enum Commands {
         #[clap(long, short)]
         output: Option<String>,
     },
+    CsqFilter {
+        input: Option<String>,
+        #[clap(long, short)]
+        output: Option<String>,
+        #[clap(long)]
+        min_severity: Option<String>,
+        #[clap(long, takes_value = false)]
+        picked: bool,
+        #[clap(long)]
+        biotype: Option<String>,
+        #[clap(long, takes_value = false)]
+        canonical: bool,
+        #[clap(long)]
+        genes: Option<String>,
+        #[clap(long)]
+        transcripts: Option<String>,
+        #[clap(long, takes_value = false)]
+        keep_bcsq_subset: bool,
+        #[clap(long, value_parser, default_value_t = 1)]
+        threads: usize,
+    },
     GLKUP {
         input: Option<String>,
         #[clap(long, short)]
         output: Option<String>,
         #[clap(long, short)]
         fields: Option<String>,
+        #[clap(long, short)]
+        gff: Option<String>,
         #[clap(long, takes_value = false)]
         dbnsfp: bool,
+        #[clap(long)]
+        gnomad_table: Option<String>,
+        #[clap(long)]
+        dbnsfp_table: Option<String>,
+        #[clap(long, default_value = ";")]
+        dbnsfp_sep: String,
+        #[clap(long)]
+        table: Vec<String>,
+        #[clap(long)]
+        key_column: Vec<String>,
+        #[clap(long)]
+        columns: Vec<String>,
+        #[clap(long)]
+        prefix: Vec<String>,
+        #[clap(long, takes_value = false)]
+        all_genes: bool,
+        #[clap(long)]
+        panel: Vec<String>,
+        #[clap(long, takes_value = false)]
+        panel_only: bool,
+        #[clap(long)]
+        clingen: Option<String>,
+        #[clap(long)]
+        moi: Vec<String>,
+        #[clap(long, value_parser, default_value_t = 1)]
+        threads: usize,
+    },
+    PhenoRank {
+        input: Option<String>,
+        #[clap(long, short)]
+        output: Option<String>,
+        #[clap(long, short)]
+        fields: Option<String>,
+        #[clap(long)]
+        hpo: Option<String>,
+        #[clap(long)]
+        obo: Option<String>,
+        #[clap(long)]
+        dbnsfp_table: Option<String>,
+        #[clap(long, default_value = ";")]
+        dbnsfp_sep: String,
+        #[clap(long, short)]
+        gff: Option<String>,
+        #[clap(long, value_parser, default_value_t = 1)]
+        threads: usize,
+    },
+    VLKUP {
+        input: Option<String>,
+        #[clap(long, short)]
+        output: Option<String>,
+        #[clap(long)]
+        source: Vec<String>,
+        #[clap(long)]
+        columns: Vec<String>,
+        #[clap(long)]
+        ops: Vec<String>,
+        #[clap(long)]
+        prefix: Vec<String>,
         #[clap(long, value_parser, default_value_t = 1)]
         threads: usize,
     }
 }
 
+#[derive(Subcommand)]
+enum McsqCommands {
+    BuildIndex {
+        #[clap(long, short)]
+        gff: Option<String>,
+        #[clap(long, short)]
+        output: Option<String>,
+    },
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -60,14 +174,26 @@ fn main() {
         Commands::FamFreq { input, output, pedigree } => {
             fam_freq::fam_freq(pedigree.as_deref())
         }
-        Commands::MCSQ { input, output, gff, threads } => {
-            mcsq::mcsq(input.as_deref(), output.as_deref(), gff.as_deref(), threads)
+        Commands::MCSQ { command: Some(McsqCommands::BuildIndex { gff, output }), .. } => {
+            mcsq::build_index(gff.as_deref(), output.as_deref())
+        }
+        Commands::MCSQ { command: None, input, output, gff, trx_index, per_allele, per_gene, emit_ranks, explain, lof, nmd, hgvs, fasta, validate, threads } => {
+            mcsq::mcsq(input.as_deref(), output.as_deref(), gff.as_deref(), trx_index.as_deref(), per_allele, per_gene, emit_ranks, explain.as_deref(), lof, nmd, hgvs, fasta.as_deref(), validate, threads)
         }
         Commands::MNV { input, output } => {
             mnv::mnv(input.as_deref(), output.as_deref())
         }
-        Commands::GLKUP { input, output, fields, dbnsfp, threads } => {
-            glkup::glkup(input.as_deref(), output.as_deref(), fields.as_deref(), dbnsfp, threads)
+        Commands::CsqFilter { input, output, min_severity, picked, biotype, canonical, genes, transcripts, keep_bcsq_subset, threads } => {
+            csq_filter::csq_filter(input.as_deref(), output.as_deref(), min_severity.as_deref(), picked, biotype.as_deref(), canonical, genes.as_deref(), transcripts.as_deref(), keep_bcsq_subset, threads)
+        }
+        Commands::GLKUP { input, output, fields, gff, dbnsfp, gnomad_table, dbnsfp_table, dbnsfp_sep, table, key_column, columns, prefix, all_genes, panel, panel_only, clingen, moi, threads } => {
+            glkup::glkup(input.as_deref(), output.as_deref(), fields.as_deref(), gff.as_deref(), dbnsfp, gnomad_table.as_deref(), dbnsfp_table.as_deref(), dbnsfp_sep, table, key_column, columns, prefix, all_genes, panel, panel_only, clingen.as_deref(), moi, threads)
+        }
+        Commands::PhenoRank { input, output, fields, hpo, obo, dbnsfp_table, dbnsfp_sep, gff, threads } => {
+            pheno_rank::pheno_rank(input.as_deref(), output.as_deref(), fields.as_deref(), hpo.as_deref(), obo.as_deref(), dbnsfp_table.as_deref(), dbnsfp_sep, gff.as_deref(), threads)
+        }
+        Commands::VLKUP { input, output, source, columns, ops, prefix, threads } => {
+            vlkup::vlkup(input.as_deref(), output.as_deref(), source, columns, ops, prefix, threads)
         }
     }
 }
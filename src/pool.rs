@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+/// gene table values stored once each, most columns are a handful of values and a lot of .
+#[derive(Debug, Default)]
+pub struct StringPool {
+    strings: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl StringPool {
+    pub fn intern(&mut self, s: &str) -> u32 {
+        if let Some(id) = self.ids.get(s) {
+            return *id;
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(s.to_string());
+        self.ids.insert(s.to_string(), id);
+        return id;
+    }
+
+    pub fn get(&self, id: u32) -> &str {
+        return &self.strings[id as usize];
+    }
+
+    /// drop the lookup once the table is read, only get is needed after that
+    pub fn freeze(&mut self) {
+        self.ids = HashMap::new();
+        self.strings.shrink_to_fit();
+    }
+}